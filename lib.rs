@@ -1,17 +1,268 @@
 #![cfg_attr(not(feature = "std"), no_std, no_main)]
 
-#[openbrush::implementation(Ownable, PSP34, PSP34Metadata)]
+#[openbrush::implementation(Ownable, Pausable, PSP34, PSP34Metadata, PSP34Enumerable)]
 #[openbrush::contract]
 pub mod dropspace_sale {
+    use ink::env::hash::{Blake2x256, HashOutput};
     use ink::primitives::AccountId as Address;
+    use ink::storage::Mapping;
     use ink_prelude::format;
     use ink_prelude::string::String as PreludeString;
+    use ink_prelude::vec::Vec as PreludeVec;
     use openbrush::{
-        contracts::psp34::{psp34, PSP34Error},
+        contracts::pausable::PausableError,
+        contracts::psp22::{PSP22Ref, PSP22},
+        contracts::psp34::{psp34, PSP34Error, PSP34Ref},
+        contracts::reentrancy_guard::*,
         modifiers,
         traits::Storage,
     };
 
+    /// Upper bound on tokens minted by a single `airdrop` call, keeps it within block weight.
+    pub const MAX_AIRDROP_PER_CALL: u128 = 100;
+
+    /// Upper bound on refund expiries paid out by a single `release_refund_reserve` call.
+    pub const MAX_RESERVE_RELEASES_PER_CALL: u32 = 100;
+
+    /// Denominator for every basis-point setting in the contract.
+    pub const BPS_DENOMINATOR: u128 = 10_000;
+
+    /// Currency a purchase can be paid in, each one is priced independently.
+    #[derive(Debug, Clone, Copy, PartialEq, Eq, scale::Encode, scale::Decode)]
+    #[cfg_attr(
+        feature = "std",
+        derive(scale_info::TypeInfo, ink::storage::traits::StorageLayout)
+    )]
+    pub enum PaymentAsset {
+        Native,
+        Psp22(Address),
+    }
+
+    /// How the native price moves with supply. `mint_price` is the price of the first token and
+    /// increments are added per token (`Linear`) or per `step_size` tokens (`Step`) minted.
+    #[derive(Debug, Clone, Copy, Default, PartialEq, Eq, scale::Encode, scale::Decode)]
+    #[cfg_attr(
+        feature = "std",
+        derive(scale_info::TypeInfo, ink::storage::traits::StorageLayout)
+    )]
+    pub enum PricingModel {
+        #[default]
+        Fixed,
+        Linear {
+            increment: u128,
+        },
+        Step {
+            step_size: u128,
+            increment: u128,
+        },
+    }
+
+    /// Promo code terms, stored under the Blake2x256 hash of the code.
+    #[derive(Debug, Clone, Default, PartialEq, Eq, scale::Encode, scale::Decode)]
+    #[cfg_attr(
+        feature = "std",
+        derive(scale_info::TypeInfo, ink::storage::traits::StorageLayout)
+    )]
+    pub struct PromoCode {
+        pub discount_bps: u128,
+        pub discount_fee: bool,
+        pub max_uses: u32,
+        pub uses: u32,
+        pub expires_at: u64,
+    }
+
+    /// Running token counters for the sale, kept up to date by every mint path.
+    #[derive(Debug, Clone, Default, PartialEq, Eq, scale::Encode, scale::Decode)]
+    #[cfg_attr(
+        feature = "std",
+        derive(scale_info::TypeInfo, ink::storage::traits::StorageLayout)
+    )]
+    pub struct SaleStats {
+        pub tokens_sold: u128,
+        pub tokens_reserved: u128,
+        pub tokens_redeemed: u128,
+        pub unique_buyers: u128,
+    }
+
+    /// Raffle round: entries are taken in a window, winners are drawn afterwards from the
+    /// committed seed mixed with a hash chained over every entry.
+    #[derive(Debug, Clone, Default, PartialEq, Eq, scale::Encode, scale::Decode)]
+    #[cfg_attr(
+        feature = "std",
+        derive(scale_info::TypeInfo, ink::storage::traits::StorageLayout)
+    )]
+    pub struct Raffle {
+        pub entry_start: u64,
+        pub entry_end: u64,
+        pub reveal_deadline: u64,
+        pub winners: u32,
+        pub max_tickets_per_wallet: u32,
+        pub ticket_price: u128,
+        pub ticket_fee: u128,
+        pub seed_hash: [u8; 32],
+        pub entropy: Option<[u8; 32]>,
+        pub entries_hash: [u8; 32],
+        pub tickets: u32,
+        pub drawn: u32,
+        pub abandoned: bool,
+    }
+
+    #[derive(Debug, Clone, Default, PartialEq, Eq, scale::Encode, scale::Decode)]
+    #[cfg_attr(
+        feature = "std",
+        derive(scale_info::TypeInfo, ink::storage::traits::StorageLayout)
+    )]
+    pub struct RaffleEntry {
+        pub tickets: u32,
+        pub wins: u32,
+        pub claimed: bool,
+        pub refunded: bool,
+    }
+
+    /// Edition tier with its own cap, native price and metadata prefix, all within the global
+    /// supply limit.
+    #[derive(Debug, Clone, Default, PartialEq, Eq, scale::Encode, scale::Decode)]
+    #[cfg_attr(
+        feature = "std",
+        derive(scale_info::TypeInfo, ink::storage::traits::StorageLayout)
+    )]
+    pub struct Tier {
+        pub name: PreludeString,
+        pub supply_limit: u128,
+        pub price: u128,
+        pub fee: u128,
+        pub base_uri: PreludeString,
+        pub minted: u128,
+    }
+
+    /// What was paid for a token through `buy` and how long it can be refunded for.
+    #[derive(Debug, Clone, Default, PartialEq, Eq, scale::Encode, scale::Decode)]
+    #[cfg_attr(
+        feature = "std",
+        derive(scale_info::TypeInfo, ink::storage::traits::StorageLayout)
+    )]
+    pub struct Payment {
+        pub paid_at: u64,
+        pub price: u128,
+        pub fee: u128,
+        pub refundable_until: u64,
+    }
+
+    /// English auction for a single 1/1 token id, the highest bid is held until settlement.
+    #[derive(Debug, Clone, Default, PartialEq, Eq, scale::Encode, scale::Decode)]
+    #[cfg_attr(
+        feature = "std",
+        derive(scale_info::TypeInfo, ink::storage::traits::StorageLayout)
+    )]
+    pub struct Auction {
+        pub start_time: u64,
+        pub end_time: u64,
+        pub reserve_price: u128,
+        pub min_increment: u128,
+        pub extension: u64,
+        pub fee: u128,
+        pub highest_bid: u128,
+        pub highest_bidder: Option<Address>,
+        pub settled: bool,
+    }
+
+    /// Snapshot of the sale configuration and live counters, read in a single call.
+    #[derive(Debug, Clone, PartialEq, Eq, scale::Encode, scale::Decode)]
+    #[cfg_attr(feature = "std", derive(scale_info::TypeInfo))]
+    pub struct SaleInfo {
+        pub base_uri: PreludeString,
+        pub supply_limit: u128,
+        pub mint_per_tx: u128,
+        pub mint_per_wallet: u128,
+        pub mint_per_block: u128,
+        pub purchase_cooldown: u64,
+        pub mint_price: u128,
+        pub mint_fee: u128,
+        pub withdraw_wallet: Option<Address>,
+        pub dev_wallet: Option<Address>,
+        pub sale_time: u64,
+        pub sale_end_time: u64,
+        pub sale_active: bool,
+        pub paused: bool,
+        pub pause_transfers: bool,
+        pub eoa_only: bool,
+        pub total_supply: u128,
+        pub block_timestamp: u64,
+    }
+
+    /// Price breakdown for a purchase or, if it would currently fail, the reason why.
+    #[derive(Debug, Clone, Default, PartialEq, Eq, scale::Encode, scale::Decode)]
+    #[cfg_attr(feature = "std", derive(scale_info::TypeInfo))]
+    pub struct Quote {
+        pub price: u128,
+        pub fee: u128,
+        pub total: u128,
+        pub discount: u128,
+        /// `(amount, unit price)` runs before any discount, in mint order.
+        pub breakdown: PreludeVec<(u128, u128)>,
+        pub rejection: Option<PSP34Error>,
+    }
+
+    /// Owner-side config changes, `None` leaves the current value untouched.
+    #[derive(Debug, Clone, Default, PartialEq, Eq, scale::Encode, scale::Decode)]
+    #[cfg_attr(feature = "std", derive(scale_info::TypeInfo))]
+    pub struct SaleConfigUpdate {
+        pub base_uri: Option<PreludeString>,
+        pub supply_limit: Option<u128>,
+        pub mint_per_tx: Option<u128>,
+        pub mint_per_wallet: Option<u128>,
+        pub mint_per_block: Option<u128>,
+        pub purchase_cooldown: Option<u64>,
+        pub mint_price: Option<u128>,
+        pub mint_fee: Option<u128>,
+        pub withdraw_wallet: Option<Option<Address>>,
+        pub sale_time: Option<u64>,
+        pub pause_transfers: Option<bool>,
+        pub eoa_only: Option<bool>,
+    }
+
+    #[ink(event)]
+    pub struct Airdrop {
+        #[ink(topic)]
+        to: Address,
+        amount: u128,
+    }
+
+    #[ink(event)]
+    pub struct Purchase {
+        #[ink(topic)]
+        buyer: Address,
+        #[ink(topic)]
+        recipient: Address,
+        amount: u128,
+        discount: u128,
+    }
+
+    #[ink(event)]
+    pub struct Redemption {
+        #[ink(topic)]
+        holder: Address,
+        amount: u128,
+    }
+
+    #[ink(event)]
+    pub struct Bid {
+        #[ink(topic)]
+        bidder: Address,
+        id: Id,
+        amount: u128,
+    }
+
+    #[ink(event)]
+    pub struct Referral {
+        #[ink(topic)]
+        referrer: Address,
+        #[ink(topic)]
+        buyer: Address,
+        amount: u128,
+        commission: u128,
+    }
+
     #[ink(storage)]
     #[derive(Default, Storage)]
     pub struct Contract {
@@ -21,14 +272,95 @@ pub mod dropspace_sale {
         ownable: ownable::Data,
         #[storage_field]
         metadata: metadata::Data,
+        #[storage_field]
+        pausable: pausable::Data,
+        #[storage_field]
+        enumerable: enumerable::Data,
+        #[storage_field]
+        guard: reentrancy_guard::Data,
         base_uri: PreludeString,
         supply_limit: u128,
         mint_per_tx: u128,
-        mint_price: u128,
-        mint_fee: u128,
         withdraw_wallet: Option<Address>,
         dev_wallet: Option<Address>,
         sale_time: u64,
+        pause_transfers: bool,
+        mint_per_wallet: u128,
+        minted: Mapping<Address, u128>,
+        payment_assets: PreludeVec<PaymentAsset>,
+        payment_prices: Mapping<PaymentAsset, (u128, u128)>,
+        stats: SaleStats,
+        raised: Mapping<PaymentAsset, (u128, u128)>,
+        refunded: Mapping<PaymentAsset, u128>,
+        withdrawn: Mapping<Address, u128>,
+        escrowed: u128,
+        eoa_only: bool,
+        trusted_callers: Mapping<Address, ()>,
+        mint_per_block: u128,
+        block_minted: (u32, u128),
+        purchase_cooldown: u64,
+        last_purchase: Mapping<Address, u64>,
+        referral_bps: u128,
+        referral_balances: Mapping<Address, u128>,
+        referral_totals: Mapping<Address, (u128, u128)>,
+        holder_collection: Option<Address>,
+        holder_price: u128,
+        holder_claims: Mapping<(Address, Id), ()>,
+        pass_collection: Option<Address>,
+        redemption_start: u64,
+        redemption_end: u64,
+        redeemed_passes: Mapping<(Address, Id), ()>,
+        raffle: Option<Raffle>,
+        raffle_tickets: Mapping<u32, Address>,
+        raffle_entries: Mapping<Address, RaffleEntry>,
+        auctions: Mapping<Id, Auction>,
+        pending_mints: u128,
+        tiers: Mapping<u32, Tier>,
+        tier_count: u32,
+        token_tiers: Mapping<u128, u32>,
+        sale_end_time: u64,
+        crowdfund: bool,
+        funding_target: u128,
+        min_sales: u128,
+        crowdfund_held: (u128, u128),
+        crowdfund_sold: u128,
+        funds_released: bool,
+        token_payments: Mapping<u128, Payment>,
+        refund_window: u64,
+        refund_fee: bool,
+        refund_reserve: (u128, u128),
+        refund_window_end: u64,
+        refund_reserves: Mapping<u64, (u128, u128)>,
+        refund_expiries: Mapping<u32, u64>,
+        refund_expiry_head: u32,
+        refund_expiry_count: u32,
+        pricing: PricingModel,
+        next_token_id: u128,
+        tokens_minted: u128,
+        bulk_discounts: PreludeVec<(u128, u128)>,
+        discount_fee: bool,
+        promo_codes: Mapping<[u8; 32], PromoCode>,
+    }
+
+    #[overrider(psp34::Internal)]
+    fn _before_token_transfer(
+        &mut self,
+        from: Option<&AccountId>,
+        to: Option<&AccountId>,
+        _id: &Id,
+    ) -> Result<(), PSP34Error> {
+        // Mints are gated by the pause checks on the sale messages, this only covers
+        // holder-to-holder transfers.
+        if from.is_some()
+            && to.is_some()
+            && self.pause_transfers
+            && pausable::Internal::_paused(self)
+        {
+            return Err(PSP34Error::Custom(String::from(
+                "DropspaceSale::transfer: Transfers are paused",
+            )));
+        }
+        Ok(())
     }
 
     impl Contract {
@@ -49,14 +381,17 @@ pub mod dropspace_sale {
                 base_uri,
                 supply_limit,
                 mint_per_tx,
-                mint_price,
-                mint_fee,
                 withdraw_wallet,
                 dev_wallet,
                 sale_time,
                 ..Default::default()
             };
 
+            _instance.payment_assets.push(PaymentAsset::Native);
+            _instance
+                .payment_prices
+                .insert(PaymentAsset::Native, &(mint_price, mint_fee));
+
             ownable::Internal::_init_with_owner(&mut _instance, Self::env().caller());
             let collection_id = PSP34::collection_id(&_instance);
             metadata::Internal::_set_attribute(
@@ -74,15 +409,23 @@ pub mod dropspace_sale {
             _instance
         }
 
-        fn mint_token(&mut self) -> Result<(), PSP34Error> {
-            let current_supply: u128 = psp34::PSP34::total_supply(self);
-            psp34::Internal::_mint_to(self, Self::env().caller(), Id::U128(current_supply))?;
-            Ok(())
+        fn mint_token(&mut self, to: Address) -> Result<u128, PSP34Error> {
+            let mut next_id: u128 = self.next_token_id;
+            // Ids put up for auction are never handed out sequentially, and ids burned by
+            // refunds aren't handed out again
+            while self.auctions.contains(Id::U128(next_id)) {
+                next_id = next_id.saturating_add(1);
+            }
+            psp34::Internal::_mint_to(self, to, Id::U128(next_id))?;
+            self.next_token_id = next_id.saturating_add(1);
+            self.tokens_minted = self.tokens_minted.saturating_add(1);
+            Ok(next_id)
         }
 
         #[ink(message)]
+        #[modifiers(when_not_paused)]
         pub fn reserve(&mut self, amount: u128) -> Result<(), PSP34Error> {
-            let current_supply: u128 = psp34::PSP34::total_supply(self);
+            let current_supply: u128 = self.allocated_supply();
             if current_supply.saturating_add(amount) > self.supply_limit {
                 return Err(PSP34Error::Custom(String::from(
                     "DropspaceSale::reserve: Supply limit reached",
@@ -90,62 +433,248 @@ pub mod dropspace_sale {
             }
 
             for _i in 0..amount {
-                let __ = self.mint_token();
+                let __ = self.mint_token(Self::env().caller());
+            }
+            self.stats.tokens_reserved = self.stats.tokens_reserved.saturating_add(amount);
+
+            Ok(())
+        }
+
+        #[ink(message)]
+        #[modifiers(only_owner, when_not_paused)]
+        pub fn airdrop(
+            &mut self,
+            recipients: PreludeVec<(Address, u128)>,
+        ) -> Result<(), PSP34Error> {
+            let total_amount = recipients
+                .iter()
+                .fold(0u128, |total, (_, amount)| total.saturating_add(*amount));
+            let current_supply: u128 = self.allocated_supply();
+
+            if total_amount > MAX_AIRDROP_PER_CALL {
+                return Err(PSP34Error::Custom(String::from(
+                    "DropspaceSale::airdrop: Can't exceed amount of mints per call",
+                )));
+            }
+
+            if current_supply.saturating_add(total_amount) > self.supply_limit {
+                return Err(PSP34Error::Custom(String::from(
+                    "DropspaceSale::airdrop: Supply limit reached",
+                )));
+            }
+
+            for (to, amount) in recipients {
+                for _i in 0..amount {
+                    self.mint_token(to)?;
+                }
+                self.stats.tokens_reserved = self.stats.tokens_reserved.saturating_add(amount);
+                self.env().emit_event(Airdrop { to, amount });
             }
 
             Ok(())
         }
 
         #[ink(message, payable)]
+        #[modifiers(non_reentrant)]
         pub fn buy(&mut self, amount: u128) -> Result<(), PSP34Error> {
-            let total_price = amount.saturating_mul(self.mint_price.saturating_add(self.mint_fee));
-            let current_supply: u128 = psp34::PSP34::total_supply(self);
+            self.buy_to(Self::env().caller(), amount, None, None)
+        }
 
-            if self.env().block_timestamp() < self.sale_time {
+        #[ink(message, payable)]
+        #[modifiers(non_reentrant)]
+        pub fn buy_for(&mut self, recipient: Address, amount: u128) -> Result<(), PSP34Error> {
+            self.buy_to(recipient, amount, None, None)
+        }
+
+        #[ink(message, payable)]
+        #[modifiers(non_reentrant)]
+        pub fn buy_with_referrer(
+            &mut self,
+            amount: u128,
+            referrer: Address,
+        ) -> Result<(), PSP34Error> {
+            let buyer = Self::env().caller();
+            if referrer == buyer {
                 return Err(PSP34Error::Custom(String::from(
-                    "DropspaceSale::buy: Sale hasn't started yet",
+                    "DropspaceSale::buy_with_referrer: Can't refer yourself",
                 )));
             }
-
-            if current_supply.saturating_add(amount) > self.supply_limit {
+            // Commissions can't be clawed back if the token is refunded
+            if self.crowdfund || self.refund_window > 0 {
                 return Err(PSP34Error::Custom(String::from(
-                    "DropspaceSale::buy: Supply limit reached",
+                    "DropspaceSale::buy_with_referrer: Referrals not available for refundable purchases",
                 )));
             }
+            self.buy_to(buyer, amount, Some(referrer), None)
+        }
 
-            if amount > self.mint_per_tx {
+        /// Buys with a promo code, the code is hashed here and matched against the registry.
+        #[ink(message, payable)]
+        #[modifiers(non_reentrant)]
+        pub fn buy_with_code(
+            &mut self,
+            amount: u128,
+            code: PreludeString,
+        ) -> Result<(), PSP34Error> {
+            let mut code_hash = <Blake2x256 as HashOutput>::Type::default();
+            ink::env::hash_bytes::<Blake2x256>(code.as_bytes(), &mut code_hash);
+            let mut promo =
+                self.promo_codes
+                    .get(code_hash)
+                    .ok_or(PSP34Error::Custom(String::from(
+                        "DropspaceSale::buy_with_code: Invalid promo code",
+                    )))?;
+
+            if self.env().block_timestamp() >= promo.expires_at {
                 return Err(PSP34Error::Custom(String::from(
-                    "DropspaceSale::buy: Can't exceed amount of mints per tx",
+                    "DropspaceSale::buy_with_code: Promo code expired",
+                )));
+            }
+            if promo.uses >= promo.max_uses {
+                return Err(PSP34Error::Custom(String::from(
+                    "DropspaceSale::buy_with_code: Promo code used up",
                 )));
             }
 
-            if self.env().transferred_value() < total_price {
+            self.buy_to(Self::env().caller(), amount, None, Some(&promo))?;
+            promo.uses = promo.uses.saturating_add(1);
+            self.promo_codes.insert(code_hash, &promo);
+            Ok(())
+        }
+
+        fn buy_to(
+            &mut self,
+            recipient: Address,
+            amount: u128,
+            referrer: Option<Address>,
+            promo: Option<&PromoCode>,
+        ) -> Result<(), PSP34Error> {
+            let (mut price, mut fee, mut discount) = self.checkout(
+                PaymentAsset::Native,
+                Self::env().caller(),
+                recipient,
+                amount,
+            )?;
+
+            // Promo codes stack on top of the bulk discount
+            if let Some(promo) = promo {
+                let (price_discount, fee_discount) =
+                    Self::discount_by(promo.discount_bps, promo.discount_fee, price, fee);
+                price = price.saturating_sub(price_discount);
+                fee = fee.saturating_sub(fee_discount);
+                discount = discount.saturating_add(price_discount.saturating_add(fee_discount));
+            }
+
+            if self.env().transferred_value() < price.saturating_add(fee) {
                 return Err(PSP34Error::Custom(String::from(
                     "DropspaceSale::buy: Wrong amount paid.",
                 )));
             }
 
-            for _i in 0..amount {
-                let __ = self.mint_token();
+            let token_ids = self.mint_purchased(Self::env().caller(), recipient, amount)?;
+            self.record_sale(PaymentAsset::Native, price, fee);
+
+            // The commission comes out of the withdraw wallet's share and stays in the
+            // contract until the referrer claims it.
+            if let Some(referrer) = referrer {
+                let commission = price.saturating_mul(self.referral_bps) / BPS_DENOMINATOR;
+                price = price.saturating_sub(commission);
+                self.credit_referral(referrer, amount, commission);
             }
 
-            if let Some(withdraw_wallet) = self.withdraw_wallet {
-                self.env()
-                    .transfer(withdraw_wallet, amount.saturating_mul(self.mint_price))
-                    .map_err(|_| {
-                        PSP34Error::Custom(String::from("Transfer to owner wallet failed"))
+            self.collect_proceeds(&token_ids, price, fee)?;
+
+            self.env().emit_event(Purchase {
+                buyer: Self::env().caller(),
+                recipient,
+                amount,
+                discount,
+            });
+
+            Ok(())
+        }
+
+        /// Pays native sale proceeds out to the sale wallets or, while crowdfunding, holds them
+        /// against each token so they can be refunded.
+        fn collect_proceeds(
+            &mut self,
+            token_ids: &[u128],
+            price: u128,
+            fee: u128,
+        ) -> Result<(), PSP34Error> {
+            let count = token_ids.len() as u128;
+
+            if self.crowdfund {
+                self.record_payments(token_ids, price, fee, 0);
+                let (held_price, held_fee) = self.crowdfund_held;
+                self.crowdfund_held = (
+                    held_price.saturating_add(price),
+                    held_fee.saturating_add(fee),
+                );
+                self.crowdfund_sold = self.crowdfund_sold.saturating_add(count);
+                self.escrowed = self.escrowed.saturating_add(price.saturating_add(fee));
+                return Ok(());
+            }
+
+            if self.refund_window > 0 {
+                let held_fee = if self.refund_fee { fee } else { 0 };
+                let refundable_until = self
+                    .env()
+                    .block_timestamp()
+                    .saturating_add(self.refund_window);
+                self.record_payments(token_ids, price, held_fee, refundable_until);
+                let (reserved_price, reserved_fee) = self.refund_reserve;
+                self.refund_reserve = (
+                    reserved_price.saturating_add(price),
+                    reserved_fee.saturating_add(held_fee),
+                );
+                self.refund_window_end = self.refund_window_end.max(refundable_until);
+
+                // Purchases sharing an expiry share a reserve entry, released once it passes
+                let (expiring_price, expiring_fee) =
+                    match self.refund_reserves.get(refundable_until) {
+                        Some(reserve) => reserve,
+                        None => {
+                            self.refund_expiries
+                                .insert(self.refund_expiry_count, &refundable_until);
+                            self.refund_expiry_count = self.refund_expiry_count.saturating_add(1);
+                            (0, 0)
+                        }
+                    };
+                self.refund_reserves.insert(
+                    refundable_until,
+                    &(
+                        expiring_price.saturating_add(price),
+                        expiring_fee.saturating_add(held_fee),
+                    ),
+                );
+                self.escrowed = self.escrowed.saturating_add(price.saturating_add(held_fee));
+
+                let fee = fee.saturating_sub(held_fee);
+                if fee > 0 {
+                    let dev_wallet = self
+                        .dev_wallet
+                        .ok_or(PSP34Error::Custom(String::from("Developer wallet not set")))?;
+                    self.pay_out(dev_wallet, fee).map_err(|_| {
+                        PSP34Error::Custom(String::from("Transfer to dev wallet failed"))
                     })?;
+                }
+                return Ok(());
+            }
+
+            if let Some(withdraw_wallet) = self.withdraw_wallet {
+                self.pay_out(withdraw_wallet, price).map_err(|_| {
+                    PSP34Error::Custom(String::from("Transfer to owner wallet failed"))
+                })?;
             } else {
                 return Err(PSP34Error::Custom(String::from("Owner wallet not set")));
             }
 
             if let Some(dev_wallet) = self.dev_wallet {
-                if amount.saturating_mul(self.mint_fee) > 0 {
-                    self.env()
-                        .transfer(dev_wallet, amount.saturating_mul(self.mint_fee))
-                        .map_err(|_| {
-                            PSP34Error::Custom(String::from("Transfer to dev wallet failed"))
-                        })?;
+                if fee > 0 {
+                    self.pay_out(dev_wallet, fee).map_err(|_| {
+                        PSP34Error::Custom(String::from("Transfer to dev wallet failed"))
+                    })?;
                 }
             } else {
                 return Err(PSP34Error::Custom(String::from("Developer wallet not set")));
@@ -154,260 +683,4455 @@ pub mod dropspace_sale {
             Ok(())
         }
 
-        #[ink(message)]
-        #[modifiers(only_owner)]
-        pub fn set_base_uri(&mut self, uri: PreludeString) -> Result<(), PSP34Error> {
-            self.base_uri = uri;
-            Ok(())
+        /// Records what each token paid. Batch totals are split evenly with the remainder on the
+        /// first tokens, so refunding the whole batch returns exactly the total.
+        fn record_payments(
+            &mut self,
+            token_ids: &[u128],
+            price: u128,
+            fee: u128,
+            refundable_until: u64,
+        ) {
+            let paid_at = self.env().block_timestamp();
+            let count = token_ids.len() as u128;
+            for (index, token_id) in token_ids.iter().enumerate() {
+                let share =
+                    |total: u128| total / count + u128::from((index as u128) < total % count);
+                self.token_payments.insert(
+                    token_id,
+                    &Payment {
+                        paid_at,
+                        price: share(price),
+                        fee: share(fee),
+                        refundable_until,
+                    },
+                );
+            }
         }
 
+        /// Starts all-or-nothing mode: `buy` proceeds are held until `sale_end_time`, then either
+        /// released to the owner or refunded if the target price raise or sale count was missed.
         #[ink(message)]
         #[modifiers(only_owner)]
-        pub fn set_mint_per_tx(&mut self, mint_per_tx: u128) -> Result<(), PSP34Error> {
-            self.mint_per_tx = mint_per_tx;
+        pub fn set_crowdfund(
+            &mut self,
+            funding_target: u128,
+            min_sales: u128,
+            sale_end_time: u64,
+        ) -> Result<(), PSP34Error> {
+            if self.crowdfund_sold > 0 || self.funds_released {
+                return Err(PSP34Error::Custom(String::from(
+                    "DropspaceSale::set_crowdfund: Crowdfund already has sales",
+                )));
+            }
+            if sale_end_time <= self.sale_time {
+                return Err(PSP34Error::Custom(String::from(
+                    "DropspaceSale::set_crowdfund: Sale ends before it starts",
+                )));
+            }
+            // Raffle and auction sales pay out on claim and settlement, so they have to be
+            // done before proceeds can be held
+            if self.pending_mints > 0 {
+                return Err(PSP34Error::Custom(String::from(
+                    "DropspaceSale::set_crowdfund: Raffle or auction still pending",
+                )));
+            }
+            if self.refund_window > 0 {
+                return Err(PSP34Error::Custom(String::from(
+                    "DropspaceSale::set_crowdfund: Refund window is set",
+                )));
+            }
+            // Earlier purchases stay refundable after the window is turned off
+            if self.refund_reserve != (0, 0)
+                || self.env().block_timestamp() < self.refund_window_end
+            {
+                return Err(PSP34Error::Custom(String::from(
+                    "DropspaceSale::set_crowdfund: Refund reserve still held",
+                )));
+            }
+            self.crowdfund = true;
+            self.funding_target = funding_target;
+            self.min_sales = min_sales;
+            self.sale_end_time = sale_end_time;
             Ok(())
         }
 
+        /// Burns the caller's tokens and returns what was paid for them, either after a failed
+        /// crowdfund or within the refund window.
         #[ink(message)]
-        #[modifiers(only_owner)]
-        pub fn set_mint_price(&mut self, mint_price: u128) -> Result<(), PSP34Error> {
-            self.mint_price = mint_price;
-            Ok(())
-        }
+        #[modifiers(non_reentrant)]
+        pub fn refund(&mut self, ids: PreludeVec<u128>) -> Result<(), PSP34Error> {
+            let holder = Self::env().caller();
+            let now = self.env().block_timestamp();
 
-        #[ink(message)]
-        #[modifiers(only_owner)]
-        pub fn set_sale_time(&mut self, sale_time: u64) -> Result<(), PSP34Error> {
-            self.sale_time = sale_time;
-            Ok(())
-        }
+            if self.crowdfund && (now < self.sale_end_time || self.funding_reached()) {
+                return Err(PSP34Error::Custom(String::from(
+                    "DropspaceSale::refund: Refunds not available",
+                )));
+            }
 
-        #[ink(message)]
-        #[modifiers(only_owner)]
-        pub fn toggle_sale_active(&mut self) -> Result<(), PSP34Error> {
-            if self.sale_time() != 0 {
-                self.sale_time = 0;
-            } else {
-                self.sale_time = u64::MAX;
+            let mut total: u128 = 0;
+            for token_id in ids {
+                let payment =
+                    self.token_payments
+                        .get(token_id)
+                        .ok_or(PSP34Error::Custom(String::from(
+                            "DropspaceSale::refund: Token not refundable",
+                        )))?;
+                if !self.crowdfund && now >= payment.refundable_until {
+                    return Err(PSP34Error::Custom(String::from(
+                        "DropspaceSale::refund: Refund window closed",
+                    )));
+                }
+                if psp34::PSP34::owner_of(self, Id::U128(token_id)) != Some(holder) {
+                    return Err(PSP34Error::Custom(String::from(
+                        "DropspaceSale::refund: Caller doesn't hold token",
+                    )));
+                }
+                psp34::Internal::_burn_from(self, holder, Id::U128(token_id))?;
+                self.token_payments.remove(token_id);
+                if let Some(tier_id) = self.token_tiers.get(token_id) {
+                    self.token_tiers.remove(token_id);
+                    if let Some(mut tier) = self.tiers.get(tier_id) {
+                        tier.minted = tier.minted.saturating_sub(1);
+                        self.tiers.insert(tier_id, &tier);
+                    }
+                }
+                if !self.crowdfund {
+                    let (reserved_price, reserved_fee) = self.refund_reserve;
+                    self.refund_reserve = (
+                        reserved_price.saturating_sub(payment.price),
+                        reserved_fee.saturating_sub(payment.fee),
+                    );
+                    let (expiring_price, expiring_fee) = self
+                        .refund_reserves
+                        .get(payment.refundable_until)
+                        .unwrap_or_default();
+                    self.refund_reserves.insert(
+                        payment.refundable_until,
+                        &(
+                            expiring_price.saturating_sub(payment.price),
+                            expiring_fee.saturating_sub(payment.fee),
+                        ),
+                    );
+                }
+                total = total.saturating_add(payment.price.saturating_add(payment.fee));
             }
-            Ok(())
+
+            self.escrowed = self.escrowed.saturating_sub(total);
+            let refunded = self.refunded.get(PaymentAsset::Native).unwrap_or_default();
+            self.refunded
+                .insert(PaymentAsset::Native, &refunded.saturating_add(total));
+
+            self.env().transfer(holder, total).map_err(|_| {
+                PSP34Error::Custom(String::from("DropspaceSale::refund: Refund failed"))
+            })
         }
 
+        /// Makes `buy` purchases refundable for `refund_window` after purchase, the fee only if
+        /// `refund_fee` is set. The refundable part is held back until its window closes.
         #[ink(message)]
         #[modifiers(only_owner)]
-        pub fn set_supply_limit(&mut self, supply_limit: u128) -> Result<(), PSP34Error> {
-            let current_supply: u128 = psp34::PSP34::total_supply(self);
-            if current_supply > supply_limit {
+        pub fn set_refund_window(
+            &mut self,
+            refund_window: u64,
+            refund_fee: bool,
+        ) -> Result<(), PSP34Error> {
+            if self.crowdfund {
                 return Err(PSP34Error::Custom(String::from(
-                    "DropspaceSale::set_total_supply: Supply limit is lesser than current supply",
+                    "DropspaceSale::set_refund_window: Crowdfund refunds already apply",
                 )));
             }
-            self.supply_limit = supply_limit;
+            self.refund_window = refund_window;
+            self.refund_fee = refund_fee;
             Ok(())
         }
 
+        /// Pays the part of the refund reserve whose refund windows have closed out to the sale
+        /// wallets, in purchase order and up to `MAX_RESERVE_RELEASES_PER_CALL` expiries a call.
         #[ink(message)]
-        #[modifiers(only_owner)]
-        pub fn set_withdraw_wallet(
-            &mut self,
-            withdraw_wallet: Option<Address>,
-        ) -> Result<(), PSP34Error> {
-            self.withdraw_wallet = withdraw_wallet;
-            Ok(())
-        }
+        #[modifiers(only_owner, non_reentrant)]
+        pub fn release_refund_reserve(&mut self) -> Result<(), PSP34Error> {
+            let now = self.env().block_timestamp();
+            let (mut price, mut fee) = (0u128, 0u128);
+            let mut released: u32 = 0;
+            while self.refund_expiry_head < self.refund_expiry_count
+                && released < MAX_RESERVE_RELEASES_PER_CALL
+            {
+                let expiry = self
+                    .refund_expiries
+                    .get(self.refund_expiry_head)
+                    .unwrap_or_default();
+                if now < expiry {
+                    break;
+                }
+                let (expired_price, expired_fee) =
+                    self.refund_reserves.get(expiry).unwrap_or_default();
+                self.refund_reserves.remove(expiry);
+                self.refund_expiries.remove(self.refund_expiry_head);
+                self.refund_expiry_head = self.refund_expiry_head.saturating_add(1);
+                price = price.saturating_add(expired_price);
+                fee = fee.saturating_add(expired_fee);
+                released = released.saturating_add(1);
+            }
 
-        #[ink(message)]
-        pub fn token_uri(&self, token_id: u128) -> Result<PreludeString, PSP34Error> {
-            let base_uri = self.base_uri.clone();
-            Ok(format!("{base_uri}{token_id}"))
-        }
+            if released == 0 {
+                return Err(PSP34Error::Custom(String::from(
+                    "DropspaceSale::release_refund_reserve: Refund window still open",
+                )));
+            }
 
-        #[ink(message)]
-        pub fn supply_limit(&self) -> u128 {
-            self.supply_limit
-        }
+            let (reserved_price, reserved_fee) = self.refund_reserve;
+            self.refund_reserve = (
+                reserved_price.saturating_sub(price),
+                reserved_fee.saturating_sub(fee),
+            );
+            self.escrowed = self.escrowed.saturating_sub(price.saturating_add(fee));
 
-        #[ink(message)]
-        pub fn mint_per_tx(&self) -> u128 {
-            self.mint_per_tx
-        }
+            let withdraw_wallet = self
+                .withdraw_wallet
+                .ok_or(PSP34Error::Custom(String::from("Owner wallet not set")))?;
+            self.pay_out(withdraw_wallet, price)
+                .map_err(|_| PSP34Error::Custom(String::from("Transfer to owner wallet failed")))?;
+            if fee > 0 {
+                let dev_wallet = self
+                    .dev_wallet
+                    .ok_or(PSP34Error::Custom(String::from("Developer wallet not set")))?;
+                self.pay_out(dev_wallet, fee).map_err(|_| {
+                    PSP34Error::Custom(String::from("Transfer to dev wallet failed"))
+                })?;
+            }
 
-        #[ink(message)]
-        pub fn get_account_balance(&self) -> u128 {
-            self.env().balance()
+            Ok(())
         }
 
+        /// Pays a successful crowdfund's held proceeds out to the sale wallets.
         #[ink(message)]
-        pub fn mint_price(&self) -> u128 {
-            self.mint_price
-        }
+        #[modifiers(only_owner, non_reentrant)]
+        pub fn release_funds(&mut self) -> Result<(), PSP34Error> {
+            if !self.crowdfund
+                || self.funds_released
+                || self.env().block_timestamp() < self.sale_end_time
+                || !self.funding_reached()
+            {
+                return Err(PSP34Error::Custom(String::from(
+                    "DropspaceSale::release_funds: Funds can't be released",
+                )));
+            }
 
-        #[ink(message)]
-        pub fn mint_fee(&self) -> u128 {
-            self.mint_fee
-        }
+            let (price, fee) = self.crowdfund_held;
+            self.funds_released = true;
+            self.escrowed = self.escrowed.saturating_sub(price.saturating_add(fee));
 
-        #[ink(message)]
-        pub fn dev_wallet(&self) -> Option<Address> {
-            self.dev_wallet
-        }
+            let withdraw_wallet = self
+                .withdraw_wallet
+                .ok_or(PSP34Error::Custom(String::from("Owner wallet not set")))?;
+            self.pay_out(withdraw_wallet, price)
+                .map_err(|_| PSP34Error::Custom(String::from("Transfer to owner wallet failed")))?;
+            if fee > 0 {
+                let dev_wallet = self
+                    .dev_wallet
+                    .ok_or(PSP34Error::Custom(String::from("Developer wallet not set")))?;
+                self.pay_out(dev_wallet, fee).map_err(|_| {
+                    PSP34Error::Custom(String::from("Transfer to dev wallet failed"))
+                })?;
+            }
 
-        #[ink(message)]
-        pub fn withdraw_wallet(&self) -> Option<Address> {
-            self.withdraw_wallet
+            Ok(())
         }
 
-        #[ink(message)]
-        pub fn sale_time(&self) -> u64 {
-            self.sale_time
-        }
+        /// Buys `amount` tokens from one tier at that tier's price.
+        #[ink(message, payable)]
+        #[modifiers(non_reentrant)]
+        pub fn buy_tier(&mut self, tier_id: u32, amount: u128) -> Result<(), PSP34Error> {
+            let buyer = Self::env().caller();
+            let mut tier = self
+                .tiers
+                .get(tier_id)
+                .ok_or(PSP34Error::Custom(String::from(
+                    "DropspaceSale::buy_tier: Tier not found",
+                )))?;
+            let price = amount.saturating_mul(tier.price);
+            let fee = amount.saturating_mul(tier.fee);
 
-        #[ink(message)]
-        pub fn sale_active(&self) -> bool {
-            self.sale_time <= self.env().block_timestamp()
-        }
+            self.check_buy(buyer, buyer, amount)?;
 
-        #[ink(message)]
-        pub fn base_uri(&self) -> PreludeString {
-            self.base_uri.clone()
-        }
+            if tier.minted.saturating_add(amount) > tier.supply_limit {
+                return Err(PSP34Error::Custom(String::from(
+                    "DropspaceSale::buy_tier: Tier supply limit reached",
+                )));
+            }
 
-        #[ink(message)]
-        #[modifiers(only_owner)]
-        pub fn withdraw(&mut self) -> Result<(), PSP34Error> {
-            let contract_balance = self.get_account_balance();
+            if self.env().transferred_value() < price.saturating_add(fee) {
+                return Err(PSP34Error::Custom(String::from(
+                    "DropspaceSale::buy_tier: Wrong amount paid.",
+                )));
+            }
 
-            if contract_balance > 0 {
-                match self.env().transfer(Self::env().caller(), contract_balance) {
-                    Ok(_) => Ok(()),
-                    Err(_) => Err(PSP34Error::Custom(String::from("Withdrawal failed"))),
-                }
-            } else {
-                Err(PSP34Error::Custom(String::from("No funds to withdraw")))
+            let mut token_ids = PreludeVec::new();
+            for _i in 0..amount {
+                let token_id = self.mint_token(buyer)?;
+                self.token_tiers.insert(token_id, &tier_id);
+                token_ids.push(token_id);
             }
+            tier.minted = tier.minted.saturating_add(amount);
+            self.tiers.insert(tier_id, &tier);
+            self.record_purchase(buyer, buyer, amount);
+            self.record_sale(PaymentAsset::Native, price, fee);
+            self.collect_proceeds(&token_ids, price, fee)?;
+
+            self.env().emit_event(Purchase {
+                buyer,
+                recipient: buyer,
+                amount,
+                discount: 0,
+            });
+
+            Ok(())
+        }
+
+        /// Mints one token per `ids` entry held by the caller in the holder collection.
+        #[ink(message, payable)]
+        #[modifiers(non_reentrant)]
+        pub fn holder_claim(&mut self, ids: PreludeVec<Id>) -> Result<(), PSP34Error> {
+            let claimer = Self::env().caller();
+            // Only `buy` proceeds are held and refundable while crowdfunding
+            if self.crowdfund {
+                return Err(PSP34Error::Custom(String::from(
+                    "DropspaceSale::holder_claim: Not available while crowdfunding",
+                )));
+            }
+            let collection = self
+                .holder_collection
+                .ok_or(PSP34Error::Custom(String::from(
+                    "DropspaceSale::holder_claim: Holder claim not enabled",
+                )))?;
+            let amount = ids.len() as u128;
+            let price = amount.saturating_mul(self.holder_price);
+
+            self.check_buy(claimer, claimer, amount)?;
+
+            if self.env().transferred_value() < price {
+                return Err(PSP34Error::Custom(String::from(
+                    "DropspaceSale::holder_claim: Wrong amount paid.",
+                )));
+            }
+
+            let gate: PSP34Ref = collection.into();
+            for id in ids {
+                if self.holder_claims.contains((collection, id.clone())) {
+                    return Err(PSP34Error::Custom(String::from(
+                        "DropspaceSale::holder_claim: Token already claimed",
+                    )));
+                }
+                if gate.owner_of(id.clone()) != Some(claimer) {
+                    return Err(PSP34Error::Custom(String::from(
+                        "DropspaceSale::holder_claim: Caller doesn't hold token",
+                    )));
+                }
+                self.holder_claims.insert((collection, id), &());
+            }
+
+            self.mint_purchased(claimer, claimer, amount)?;
+            self.record_sale(PaymentAsset::Native, price, 0);
+
+            if price > 0 {
+                let withdraw_wallet = self
+                    .withdraw_wallet
+                    .ok_or(PSP34Error::Custom(String::from("Owner wallet not set")))?;
+                self.pay_out(withdraw_wallet, price).map_err(|_| {
+                    PSP34Error::Custom(String::from("Transfer to owner wallet failed"))
+                })?;
+            }
+
+            self.env().emit_event(Purchase {
+                buyer: claimer,
+                recipient: claimer,
+                amount,
+                discount: 0,
+            });
+
+            Ok(())
+        }
+
+        /// Locks the caller's mint passes in this contract and mints one token per pass.
+        /// The pass collection needs this contract approved for the given ids.
+        #[ink(message)]
+        #[modifiers(when_not_paused, non_reentrant)]
+        pub fn redeem(&mut self, pass_ids: PreludeVec<Id>) -> Result<(), PSP34Error> {
+            let holder = Self::env().caller();
+            let collection = self.pass_collection.ok_or(PSP34Error::Custom(String::from(
+                "DropspaceSale::redeem: Redemption not enabled",
+            )))?;
+            let now = self.env().block_timestamp();
+            let amount = pass_ids.len() as u128;
+            let current_supply: u128 = self.allocated_supply();
+
+            if now < self.redemption_start || now >= self.redemption_end {
+                return Err(PSP34Error::Custom(String::from(
+                    "DropspaceSale::redeem: Redemption window closed",
+                )));
+            }
+
+            if current_supply.saturating_add(amount) > self.supply_limit {
+                return Err(PSP34Error::Custom(String::from(
+                    "DropspaceSale::redeem: Supply limit reached",
+                )));
+            }
+
+            let contract = self.env().account_id();
+            let mut pass: PSP34Ref = collection.into();
+            for id in pass_ids {
+                if pass.owner_of(id.clone()) != Some(holder) {
+                    return Err(PSP34Error::Custom(String::from(
+                        "DropspaceSale::redeem: Caller doesn't hold pass",
+                    )));
+                }
+                pass.transfer(contract, id.clone(), PreludeVec::new())
+                    .map_err(|_| {
+                        PSP34Error::Custom(String::from(
+                            "DropspaceSale::redeem: Pass transfer failed",
+                        ))
+                    })?;
+                self.redeemed_passes.insert((collection, id), &());
+            }
+
+            for _i in 0..amount {
+                self.mint_token(holder)?;
+            }
+            self.stats.tokens_redeemed = self.stats.tokens_redeemed.saturating_add(amount);
+
+            self.env().emit_event(Redemption { holder, amount });
+
+            Ok(())
+        }
+
+        /// Opens a raffle for `winners` tokens. Ticket price and fee are taken from the native
+        /// price at this point, `seed_hash` is the Blake2x256 hash of the seed revealed later.
+        /// If the seed isn't revealed before `reveal_deadline` every ticket can be refunded.
+        #[ink(message)]
+        #[modifiers(only_owner)]
+        pub fn start_raffle(
+            &mut self,
+            entry_start: u64,
+            entry_end: u64,
+            reveal_deadline: u64,
+            winners: u32,
+            max_tickets_per_wallet: u32,
+            seed_hash: [u8; 32],
+        ) -> Result<(), PSP34Error> {
+            // Only `buy` proceeds are held and refundable while crowdfunding
+            if self.crowdfund {
+                return Err(PSP34Error::Custom(String::from(
+                    "DropspaceSale::start_raffle: Not available while crowdfunding",
+                )));
+            }
+            if self.raffle.is_some() {
+                return Err(PSP34Error::Custom(String::from(
+                    "DropspaceSale::start_raffle: Raffle already started",
+                )));
+            }
+            if entry_start > entry_end {
+                return Err(PSP34Error::Custom(String::from(
+                    "DropspaceSale::start_raffle: Window ends before it starts",
+                )));
+            }
+            if reveal_deadline < entry_end {
+                return Err(PSP34Error::Custom(String::from(
+                    "DropspaceSale::start_raffle: Reveal deadline before entries close",
+                )));
+            }
+            if self.allocated_supply().saturating_add(winners as u128) > self.supply_limit {
+                return Err(PSP34Error::Custom(String::from(
+                    "DropspaceSale::start_raffle: Supply limit reached",
+                )));
+            }
+
+            let (ticket_price, ticket_fee) = self.payment_price_of(PaymentAsset::Native)?;
+            self.raffle = Some(Raffle {
+                entry_start,
+                entry_end,
+                reveal_deadline,
+                winners,
+                max_tickets_per_wallet,
+                ticket_price,
+                ticket_fee,
+                seed_hash,
+                ..Default::default()
+            });
+            self.pending_mints = self.pending_mints.saturating_add(winners as u128);
+            Ok(())
+        }
+
+        /// Deposits the full price for `tickets` raffle tickets, one per token wanted.
+        #[ink(message, payable)]
+        #[modifiers(when_not_paused, non_reentrant)]
+        pub fn enter_raffle(&mut self, tickets: u32) -> Result<(), PSP34Error> {
+            let entrant = Self::env().caller();
+            let mut raffle = self.active_raffle()?;
+            let now = self.env().block_timestamp();
+            let mut entry = self.raffle_entries.get(entrant).unwrap_or_default();
+            let deposit = (tickets as u128)
+                .saturating_mul(raffle.ticket_price.saturating_add(raffle.ticket_fee));
+
+            if now < raffle.entry_start || now >= raffle.entry_end {
+                return Err(PSP34Error::Custom(String::from(
+                    "DropspaceSale::enter_raffle: Entries are closed",
+                )));
+            }
+
+            if entry.tickets.saturating_add(tickets) > raffle.max_tickets_per_wallet {
+                return Err(PSP34Error::Custom(String::from(
+                    "DropspaceSale::enter_raffle: Can't exceed amount of tickets per wallet",
+                )));
+            }
+
+            if self.env().transferred_value() < deposit {
+                return Err(PSP34Error::Custom(String::from(
+                    "DropspaceSale::enter_raffle: Wrong amount paid.",
+                )));
+            }
+
+            // Entrants add their own entropy, the seed was committed before any of it was known
+            let mut entries_hash = <Blake2x256 as HashOutput>::Type::default();
+            ink::env::hash_encoded::<Blake2x256, _>(
+                &(
+                    raffle.entries_hash,
+                    entrant,
+                    tickets,
+                    self.env().block_number(),
+                ),
+                &mut entries_hash,
+            );
+            raffle.entries_hash = entries_hash;
+
+            for _i in 0..tickets {
+                self.raffle_tickets.insert(raffle.tickets, &entrant);
+                raffle.tickets = raffle.tickets.saturating_add(1);
+            }
+            entry.tickets = entry.tickets.saturating_add(tickets);
+            self.raffle_entries.insert(entrant, &entry);
+            self.raffle = Some(raffle);
+            self.escrowed = self.escrowed.saturating_add(deposit);
+
+            Ok(())
+        }
+
+        /// Reveals the committed seed once entries are closed and fixes the draw entropy.
+        #[ink(message)]
+        #[modifiers(only_owner)]
+        pub fn reveal_raffle_seed(&mut self, seed: [u8; 32]) -> Result<(), PSP34Error> {
+            let mut raffle = self.active_raffle()?;
+
+            if self.env().block_timestamp() < raffle.entry_end {
+                return Err(PSP34Error::Custom(String::from(
+                    "DropspaceSale::reveal_raffle_seed: Entries are still open",
+                )));
+            }
+            if self.env().block_timestamp() >= raffle.reveal_deadline {
+                return Err(PSP34Error::Custom(String::from(
+                    "DropspaceSale::reveal_raffle_seed: Reveal deadline passed",
+                )));
+            }
+            if raffle.entropy.is_some() {
+                return Err(PSP34Error::Custom(String::from(
+                    "DropspaceSale::reveal_raffle_seed: Seed already revealed",
+                )));
+            }
+
+            let mut seed_hash = <Blake2x256 as HashOutput>::Type::default();
+            ink::env::hash_bytes::<Blake2x256>(&seed, &mut seed_hash);
+            if seed_hash != raffle.seed_hash {
+                return Err(PSP34Error::Custom(String::from(
+                    "DropspaceSale::reveal_raffle_seed: Seed doesn't match commitment",
+                )));
+            }
+
+            let mut entropy = <Blake2x256 as HashOutput>::Type::default();
+            // Entries are closed, so the owner can no longer influence the result by timing the reveal
+            ink::env::hash_encoded::<Blake2x256, _>(&(seed, raffle.entries_hash), &mut entropy);
+            raffle.entropy = Some(entropy);
+
+            // Entries are final now, supply nobody entered for goes back to the open sale
+            let unclaimed = raffle.winners.saturating_sub(raffle.tickets) as u128;
+            self.pending_mints = self.pending_mints.saturating_sub(unclaimed);
+            self.raffle = Some(raffle);
+            Ok(())
+        }
+
+        /// Draws up to `batch` winners, repeat until `raffle().drawn` reaches the winner count.
+        /// Past the reveal deadline without a seed it abandons the raffle instead.
+        #[ink(message)]
+        pub fn draw_raffle(&mut self, batch: u32) -> Result<(), PSP34Error> {
+            let mut raffle = self.active_raffle()?;
+            let entropy = match raffle.entropy {
+                Some(entropy) => entropy,
+                None if self.env().block_timestamp() >= raffle.reveal_deadline => {
+                    if raffle.abandoned {
+                        return Err(PSP34Error::Custom(String::from(
+                            "DropspaceSale::draw_raffle: Raffle abandoned",
+                        )));
+                    }
+                    // Releases the slots even when nobody entered and no refund will ever do it
+                    self.abandon_raffle(raffle);
+                    return Ok(());
+                }
+                None => {
+                    return Err(PSP34Error::Custom(String::from(
+                        "DropspaceSale::draw_raffle: Seed not revealed",
+                    )))
+                }
+            };
+            let winners = raffle.winners.min(raffle.tickets);
+
+            if raffle.drawn >= winners {
+                return Err(PSP34Error::Custom(String::from(
+                    "DropspaceSale::draw_raffle: Draw already complete",
+                )));
+            }
+
+            // Partial Fisher-Yates shuffle, the first `winners` ticket slots end up as winners
+            let end = raffle.drawn.saturating_add(batch).min(winners);
+            for index in raffle.drawn..end {
+                let mut random = <Blake2x256 as HashOutput>::Type::default();
+                ink::env::hash_encoded::<Blake2x256, _>(&(entropy, index), &mut random);
+                let offset = u128::from_le_bytes(random[..16].try_into().unwrap_or_default())
+                    % (raffle.tickets - index) as u128;
+                let pick = index + offset as u32;
+
+                if let (Some(current), Some(winner)) = (
+                    self.raffle_tickets.get(index),
+                    self.raffle_tickets.get(pick),
+                ) {
+                    self.raffle_tickets.insert(index, &winner);
+                    self.raffle_tickets.insert(pick, &current);
+
+                    let mut entry = self.raffle_entries.get(winner).unwrap_or_default();
+                    entry.wins = entry.wins.saturating_add(1);
+                    self.raffle_entries.insert(winner, &entry);
+                }
+            }
+            raffle.drawn = end;
+            self.raffle = Some(raffle);
+            Ok(())
+        }
+
+        /// Mints the caller's raffle wins and pays their deposits out to the sale wallets.
+        #[ink(message)]
+        #[modifiers(non_reentrant)]
+        pub fn claim_raffle(&mut self) -> Result<(), PSP34Error> {
+            let entrant = Self::env().caller();
+            let raffle = self.drawn_raffle()?;
+            let mut entry = self.raffle_entries.get(entrant).unwrap_or_default();
+
+            if entry.wins == 0 || entry.claimed {
+                return Err(PSP34Error::Custom(String::from(
+                    "DropspaceSale::claim_raffle: Nothing to claim",
+                )));
+            }
+
+            let wins = entry.wins as u128;
+            let price = wins.saturating_mul(raffle.ticket_price);
+            let fee = wins.saturating_mul(raffle.ticket_fee);
+            entry.claimed = true;
+            self.raffle_entries.insert(entrant, &entry);
+            self.pending_mints = self.pending_mints.saturating_sub(wins);
+            self.escrowed = self.escrowed.saturating_sub(price.saturating_add(fee));
+
+            self.mint_purchased(entrant, entrant, wins)?;
+            self.record_sale(PaymentAsset::Native, price, fee);
+
+            let withdraw_wallet = self
+                .withdraw_wallet
+                .ok_or(PSP34Error::Custom(String::from("Owner wallet not set")))?;
+            self.pay_out(withdraw_wallet, price)
+                .map_err(|_| PSP34Error::Custom(String::from("Transfer to owner wallet failed")))?;
+            if fee > 0 {
+                let dev_wallet = self
+                    .dev_wallet
+                    .ok_or(PSP34Error::Custom(String::from("Developer wallet not set")))?;
+                self.pay_out(dev_wallet, fee).map_err(|_| {
+                    PSP34Error::Custom(String::from("Transfer to dev wallet failed"))
+                })?;
+            }
+
+            self.env().emit_event(Purchase {
+                buyer: entrant,
+                recipient: entrant,
+                amount: wins,
+                discount: 0,
+            });
+
+            Ok(())
+        }
+
+        /// Returns the deposit for every ticket of the caller's that didn't win, or for every
+        /// ticket if the seed wasn't revealed by the deadline.
+        #[ink(message)]
+        #[modifiers(non_reentrant)]
+        pub fn refund_raffle(&mut self) -> Result<(), PSP34Error> {
+            let entrant = Self::env().caller();
+            let raffle = self.active_raffle()?;
+            let abandoned =
+                raffle.entropy.is_none() && self.env().block_timestamp() >= raffle.reveal_deadline;
+            if !abandoned {
+                self.drawn_raffle()?;
+            }
+            let mut entry = self.raffle_entries.get(entrant).unwrap_or_default();
+            let lost = if abandoned {
+                entry.tickets as u128
+            } else {
+                entry.tickets.saturating_sub(entry.wins) as u128
+            };
+
+            if lost == 0 || entry.refunded {
+                return Err(PSP34Error::Custom(String::from(
+                    "DropspaceSale::refund_raffle: Nothing to refund",
+                )));
+            }
+
+            let deposit =
+                lost.saturating_mul(raffle.ticket_price.saturating_add(raffle.ticket_fee));
+            entry.refunded = true;
+            self.raffle_entries.insert(entrant, &entry);
+            self.escrowed = self.escrowed.saturating_sub(deposit);
+
+            if abandoned && !raffle.abandoned {
+                self.abandon_raffle(raffle);
+            }
+
+            self.env().transfer(entrant, deposit).map_err(|_| {
+                PSP34Error::Custom(String::from("DropspaceSale::refund_raffle: Refund failed"))
+            })
+        }
+
+        /// Nobody can win an abandoned raffle, its slots go back to the open sale.
+        fn abandon_raffle(&mut self, mut raffle: Raffle) {
+            raffle.abandoned = true;
+            self.pending_mints = self.pending_mints.saturating_sub(raffle.winners as u128);
+            self.raffle = Some(raffle);
+        }
+
+        fn active_raffle(&self) -> Result<Raffle, PSP34Error> {
+            self.raffle.clone().ok_or(PSP34Error::Custom(String::from(
+                "DropspaceSale::raffle: No raffle running",
+            )))
+        }
+
+        fn drawn_raffle(&self) -> Result<Raffle, PSP34Error> {
+            let raffle = self.active_raffle()?;
+            if raffle.entropy.is_none() || raffle.drawn < raffle.winners.min(raffle.tickets) {
+                return Err(PSP34Error::Custom(String::from(
+                    "DropspaceSale::raffle: Draw not complete",
+                )));
+            }
+            Ok(raffle)
+        }
+
+        /// Puts token `id` up for auction, bids within `extension` of the end push the end back.
+        #[ink(message)]
+        #[modifiers(only_owner)]
+        pub fn start_auction(
+            &mut self,
+            id: Id,
+            start_time: u64,
+            end_time: u64,
+            reserve_price: u128,
+            min_increment: u128,
+            extension: u64,
+        ) -> Result<(), PSP34Error> {
+            // Only `buy` proceeds are held and refundable while crowdfunding
+            if self.crowdfund {
+                return Err(PSP34Error::Custom(String::from(
+                    "DropspaceSale::start_auction: Not available while crowdfunding",
+                )));
+            }
+            if let Some(auction) = self.auctions.get(id.clone()) {
+                // An auction that closed without bids can be run again
+                if !auction.settled || auction.highest_bidder.is_some() {
+                    return Err(PSP34Error::Custom(String::from(
+                        "DropspaceSale::start_auction: Token already auctioned",
+                    )));
+                }
+            }
+            // Ids below the sequential counter were minted already, even if burned since
+            let unminted = match id {
+                Id::U128(token_id) => token_id >= self.next_token_id,
+                _ => false,
+            };
+            if !unminted || psp34::PSP34::owner_of(self, id.clone()).is_some() {
+                return Err(PSP34Error::Custom(String::from(
+                    "DropspaceSale::start_auction: Token already minted",
+                )));
+            }
+            if start_time > end_time {
+                return Err(PSP34Error::Custom(String::from(
+                    "DropspaceSale::start_auction: Auction ends before it starts",
+                )));
+            }
+            if min_increment == 0 {
+                return Err(PSP34Error::Custom(String::from(
+                    "DropspaceSale::start_auction: Minimum increment can't be zero",
+                )));
+            }
+            if self.allocated_supply().saturating_add(1) > self.supply_limit {
+                return Err(PSP34Error::Custom(String::from(
+                    "DropspaceSale::start_auction: Supply limit reached",
+                )));
+            }
+
+            let (_, fee) = self.payment_price_of(PaymentAsset::Native)?;
+            self.auctions.insert(
+                id,
+                &Auction {
+                    start_time,
+                    end_time,
+                    reserve_price,
+                    min_increment,
+                    extension,
+                    fee,
+                    ..Default::default()
+                },
+            );
+            self.pending_mints = self.pending_mints.saturating_add(1);
+            Ok(())
+        }
+
+        /// Bids the transferred value on token `id`, the previous highest bidder is refunded.
+        #[ink(message, payable)]
+        #[modifiers(when_not_paused, non_reentrant)]
+        pub fn bid(&mut self, id: Id) -> Result<(), PSP34Error> {
+            let bidder = Self::env().caller();
+            let amount = self.env().transferred_value();
+            let now = self.env().block_timestamp();
+            let mut auction = self.open_auction(id.clone())?;
+
+            if now < auction.start_time || now >= auction.end_time {
+                return Err(PSP34Error::Custom(String::from(
+                    "DropspaceSale::bid: Auction isn't running",
+                )));
+            }
+            if amount < auction.reserve_price {
+                return Err(PSP34Error::Custom(String::from(
+                    "DropspaceSale::bid: Bid below reserve price",
+                )));
+            }
+            if auction.highest_bidder.is_some()
+                && amount < auction.highest_bid.saturating_add(auction.min_increment)
+            {
+                return Err(PSP34Error::Custom(String::from(
+                    "DropspaceSale::bid: Bid increment too low",
+                )));
+            }
+
+            let outbid = auction
+                .highest_bidder
+                .map(|previous| (previous, auction.highest_bid));
+            auction.highest_bid = amount;
+            auction.highest_bidder = Some(bidder);
+            if auction.end_time.saturating_sub(now) < auction.extension {
+                auction.end_time = now.saturating_add(auction.extension);
+            }
+            self.auctions.insert(id.clone(), &auction);
+            self.escrowed = self.escrowed.saturating_add(amount);
+
+            if let Some((previous, previous_bid)) = outbid {
+                self.escrowed = self.escrowed.saturating_sub(previous_bid);
+                self.env().transfer(previous, previous_bid).map_err(|_| {
+                    PSP34Error::Custom(String::from("DropspaceSale::bid: Refund failed"))
+                })?;
+            }
+
+            self.env().emit_event(Bid { bidder, id, amount });
+
+            Ok(())
+        }
+
+        /// Closes an ended auction, mints the token to the winner and pays out the winning bid.
+        #[ink(message)]
+        #[modifiers(non_reentrant)]
+        pub fn settle_auction(&mut self, id: Id) -> Result<(), PSP34Error> {
+            let mut auction = self.open_auction(id.clone())?;
+
+            if self.env().block_timestamp() < auction.end_time {
+                return Err(PSP34Error::Custom(String::from(
+                    "DropspaceSale::settle_auction: Auction hasn't ended yet",
+                )));
+            }
+
+            auction.settled = true;
+            self.auctions.insert(id.clone(), &auction);
+            self.pending_mints = self.pending_mints.saturating_sub(1);
+
+            let winner = match auction.highest_bidder {
+                Some(winner) => winner,
+                None => return Ok(()),
+            };
+
+            let fee = auction.fee.min(auction.highest_bid);
+            let price = auction.highest_bid.saturating_sub(fee);
+            self.escrowed = self.escrowed.saturating_sub(auction.highest_bid);
+
+            psp34::Internal::_mint_to(self, winner, id)?;
+            self.tokens_minted = self.tokens_minted.saturating_add(1);
+            self.record_purchase(winner, winner, 1);
+            self.record_sale(PaymentAsset::Native, price, fee);
+
+            let withdraw_wallet = self
+                .withdraw_wallet
+                .ok_or(PSP34Error::Custom(String::from("Owner wallet not set")))?;
+            self.pay_out(withdraw_wallet, price)
+                .map_err(|_| PSP34Error::Custom(String::from("Transfer to owner wallet failed")))?;
+            if fee > 0 {
+                let dev_wallet = self
+                    .dev_wallet
+                    .ok_or(PSP34Error::Custom(String::from("Developer wallet not set")))?;
+                self.pay_out(dev_wallet, fee).map_err(|_| {
+                    PSP34Error::Custom(String::from("Transfer to dev wallet failed"))
+                })?;
+            }
+
+            self.env().emit_event(Purchase {
+                buyer: winner,
+                recipient: winner,
+                amount: 1,
+                discount: 0,
+            });
+
+            Ok(())
+        }
+
+        fn open_auction(&self, id: Id) -> Result<Auction, PSP34Error> {
+            match self.auctions.get(id) {
+                Some(auction) if !auction.settled => Ok(auction),
+                _ => Err(PSP34Error::Custom(String::from(
+                    "DropspaceSale::auction: No auction running for token",
+                ))),
+            }
+        }
+
+        #[ink(message)]
+        #[modifiers(non_reentrant)]
+        pub fn buy_with_psp22(&mut self, token: Address, amount: u128) -> Result<(), PSP34Error> {
+            let buyer = Self::env().caller();
+            let (price, fee, discount) =
+                self.checkout(PaymentAsset::Psp22(token), buyer, buyer, amount)?;
+
+            let withdraw_wallet = self
+                .withdraw_wallet
+                .ok_or(PSP34Error::Custom(String::from("Owner wallet not set")))?;
+            let dev_wallet = self
+                .dev_wallet
+                .ok_or(PSP34Error::Custom(String::from("Developer wallet not set")))?;
+
+            let mut psp22: PSP22Ref = token.into();
+            psp22
+                .transfer_from(buyer, withdraw_wallet, price, PreludeVec::new())
+                .map_err(|_| PSP34Error::Custom(String::from("Transfer to owner wallet failed")))?;
+
+            if fee > 0 {
+                psp22
+                    .transfer_from(buyer, dev_wallet, fee, PreludeVec::new())
+                    .map_err(|_| {
+                        PSP34Error::Custom(String::from("Transfer to dev wallet failed"))
+                    })?;
+            }
+
+            self.mint_purchased(buyer, buyer, amount)?;
+            self.record_sale(PaymentAsset::Psp22(token), price, fee);
+
+            self.env().emit_event(Purchase {
+                buyer,
+                recipient: buyer,
+                amount,
+                discount,
+            });
+
+            Ok(())
+        }
+
+        fn payment_price_of(&self, asset: PaymentAsset) -> Result<(u128, u128), PSP34Error> {
+            self.payment_prices
+                .get(asset)
+                .ok_or(PSP34Error::Custom(String::from(
+                    "DropspaceSale::buy: Payment asset not accepted",
+                )))
+        }
+
+        /// Validates a purchase and prices it. Every buy path and `quote` go through here.
+        /// Returns the price and fee after discounts and the total discount given.
+        fn checkout(
+            &self,
+            asset: PaymentAsset,
+            buyer: Address,
+            recipient: Address,
+            amount: u128,
+        ) -> Result<(u128, u128, u128), PSP34Error> {
+            // Only `buy` proceeds are held and refundable while crowdfunding
+            if self.crowdfund && matches!(asset, PaymentAsset::Psp22(_)) {
+                return Err(PSP34Error::Custom(String::from(
+                    "DropspaceSale::buy_with_psp22: Not available while crowdfunding",
+                )));
+            }
+            // Limits first, they bound `amount` before anything is priced
+            self.check_buy(buyer, recipient, amount)?;
+            let (price, fee) = self.price_of(asset, amount)?;
+            let (price_discount, fee_discount) = self.bulk_discount(amount, price, fee);
+            Ok((
+                price.saturating_sub(price_discount),
+                fee.saturating_sub(fee_discount),
+                price_discount.saturating_add(fee_discount),
+            ))
+        }
+
+        /// Discount off the price and fee from the best bulk tier `amount` qualifies for.
+        fn bulk_discount(&self, amount: u128, price: u128, fee: u128) -> (u128, u128) {
+            let bps = self
+                .bulk_discounts
+                .iter()
+                .filter(|(min_quantity, _)| amount >= *min_quantity)
+                .map(|(_, bps)| *bps)
+                .max()
+                .unwrap_or_default();
+            Self::discount_by(bps, self.discount_fee, price, fee)
+        }
+
+        /// `bps` off the price, and off the fee too if `discount_fee` is set.
+        fn discount_by(bps: u128, discount_fee: bool, price: u128, fee: u128) -> (u128, u128) {
+            let fee_discount = if discount_fee {
+                fee.saturating_mul(bps) / BPS_DENOMINATOR
+            } else {
+                0
+            };
+            (price.saturating_mul(bps) / BPS_DENOMINATOR, fee_discount)
+        }
+
+        /// Total price and fee for `amount` tokens, in closed form so the cost doesn't grow
+        /// with `amount`.
+        fn price_of(&self, asset: PaymentAsset, amount: u128) -> Result<(u128, u128), PSP34Error> {
+            let (unit_price, fee) = self.payment_price_of(asset)?;
+            let mut price = amount.saturating_mul(unit_price);
+            if let Some((step_size, increment)) = self.price_steps(asset) {
+                let start = self.tokens_minted;
+                let steps = Self::steps_before(start.saturating_add(amount), step_size)
+                    .saturating_sub(Self::steps_before(start, step_size));
+                price = price.saturating_add(steps.saturating_mul(increment));
+            }
+            Ok((price, amount.saturating_mul(fee)))
+        }
+
+        /// Step size and increment the price of `asset` follows, `None` if it's fixed. Only the
+        /// native price follows the pricing model, `Linear` is a step pricing that goes up on
+        /// every token.
+        fn price_steps(&self, asset: PaymentAsset) -> Option<(u128, u128)> {
+            match (asset, self.pricing) {
+                (PaymentAsset::Native, PricingModel::Linear { increment }) => Some((1, increment)),
+                (
+                    PaymentAsset::Native,
+                    PricingModel::Step {
+                        step_size,
+                        increment,
+                    },
+                ) => Some((step_size, increment)),
+                _ => None,
+            }
+        }
+
+        /// Sum of the steps reached by each of the first `count` tokens, i.e. of
+        /// `index / step_size` for every index below `count`.
+        fn steps_before(count: u128, step_size: u128) -> u128 {
+            let full = count / step_size;
+            let rest = count % step_size;
+            // Arithmetic series over the completed steps, halving whichever factor is even
+            let series = if full % 2 == 0 {
+                (full / 2).saturating_mul(full.saturating_sub(1))
+            } else {
+                full.saturating_mul(full.saturating_sub(1) / 2)
+            };
+            series
+                .saturating_mul(step_size)
+                .saturating_add(rest.saturating_mul(full))
+        }
+
+        /// Splits a purchase of `amount` into runs of tokens at the same unit price, one per
+        /// step crossed.
+        fn price_breakdown(
+            &self,
+            asset: PaymentAsset,
+            amount: u128,
+        ) -> Result<PreludeVec<(u128, u128)>, PSP34Error> {
+            let (price, _) = self.payment_price_of(asset)?;
+            let mut breakdown = PreludeVec::new();
+
+            let (step_size, increment) = match self.price_steps(asset) {
+                Some(steps) => steps,
+                None => {
+                    breakdown.push((amount, price));
+                    return Ok(breakdown);
+                }
+            };
+
+            // Counts every token ever minted, so refunds and burns don't lower the price
+            let mut index: u128 = self.tokens_minted;
+            let mut remaining = amount;
+            while remaining > 0 {
+                let count = remaining.min(step_size - index % step_size);
+                let unit_price =
+                    price.saturating_add((index / step_size).saturating_mul(increment));
+                breakdown.push((count, unit_price));
+                index = index.saturating_add(count);
+                remaining -= count;
+            }
+            Ok(breakdown)
+        }
+
+        /// Per-wallet caps count tokens received, so gifts use up the recipient's allowance. The
+        /// cooldown follows the paying wallet.
+        fn check_buy(
+            &self,
+            buyer: Address,
+            recipient: Address,
+            amount: u128,
+        ) -> Result<(), PSP34Error> {
+            let current_supply: u128 = self.allocated_supply();
+
+            if pausable::Internal::_paused(self) {
+                return Err(PausableError::Paused.into());
+            }
+
+            let caller = self.env().caller();
+            if self.eoa_only
+                && !self.trusted_callers.contains(caller)
+                && !self.env().caller_is_origin()
+            {
+                return Err(PSP34Error::Custom(String::from(
+                    "DropspaceSale::buy: Contract callers not allowed",
+                )));
+            }
+
+            if self.env().block_timestamp() < self.sale_time {
+                return Err(PSP34Error::Custom(String::from(
+                    "DropspaceSale::buy: Sale hasn't started yet",
+                )));
+            }
+
+            if self.sale_end_time > 0 && self.env().block_timestamp() >= self.sale_end_time {
+                return Err(PSP34Error::Custom(String::from(
+                    "DropspaceSale::buy: Sale has ended",
+                )));
+            }
+
+            if current_supply.saturating_add(amount) > self.supply_limit {
+                return Err(PSP34Error::Custom(String::from(
+                    "DropspaceSale::buy: Supply limit reached",
+                )));
+            }
+
+            if amount > self.mint_per_tx {
+                return Err(PSP34Error::Custom(String::from(
+                    "DropspaceSale::buy: Can't exceed amount of mints per tx",
+                )));
+            }
+
+            let minted = self.minted.get(recipient).unwrap_or_default();
+            if self.mint_per_wallet > 0 && minted.saturating_add(amount) > self.mint_per_wallet {
+                return Err(PSP34Error::Custom(String::from(
+                    "DropspaceSale::buy: Can't exceed amount of mints per wallet",
+                )));
+            }
+
+            if self.mint_per_block > 0
+                && self.minted_this_block().saturating_add(amount) > self.mint_per_block
+            {
+                return Err(PSP34Error::Custom(String::from(
+                    "DropspaceSale::buy: Can't exceed amount of mints per block",
+                )));
+            }
+
+            if let Some(last_purchase) = self.last_purchase.get(buyer) {
+                if self.env().block_timestamp()
+                    < last_purchase.saturating_add(self.purchase_cooldown)
+                {
+                    return Err(PSP34Error::Custom(String::from(
+                        "DropspaceSale::buy: Wallet is cooling down",
+                    )));
+                }
+            }
+
+            Ok(())
+        }
+
+        /// Minted supply plus tokens set aside for raffle winners and auctions that aren't minted yet.
+        fn allocated_supply(&self) -> u128 {
+            let current_supply: u128 = psp34::PSP34::total_supply(self);
+            current_supply.saturating_add(self.pending_mints)
+        }
+
+        fn mint_purchased(
+            &mut self,
+            buyer: Address,
+            recipient: Address,
+            amount: u128,
+        ) -> Result<PreludeVec<u128>, PSP34Error> {
+            let mut token_ids = PreludeVec::new();
+            for _i in 0..amount {
+                token_ids.push(self.mint_token(recipient)?);
+            }
+            self.record_purchase(buyer, recipient, amount);
+            Ok(token_ids)
+        }
+
+        fn record_purchase(&mut self, buyer: Address, recipient: Address, amount: u128) {
+            let minted = self.minted.get(recipient).unwrap_or_default();
+            if minted == 0 && amount > 0 {
+                self.stats.unique_buyers = self.stats.unique_buyers.saturating_add(1);
+            }
+            self.stats.tokens_sold = self.stats.tokens_sold.saturating_add(amount);
+            self.minted
+                .insert(recipient, &minted.saturating_add(amount));
+
+            self.block_minted = (
+                self.env().block_number(),
+                self.minted_this_block().saturating_add(amount),
+            );
+            self.last_purchase
+                .insert(buyer, &self.env().block_timestamp());
+        }
+
+        fn minted_this_block(&self) -> u128 {
+            let (block, minted) = self.block_minted;
+            if block == self.env().block_number() {
+                minted
+            } else {
+                0
+            }
+        }
+
+        fn credit_referral(&mut self, referrer: Address, amount: u128, commission: u128) {
+            let balance = self.referral_balances.get(referrer).unwrap_or_default();
+            self.referral_balances
+                .insert(referrer, &balance.saturating_add(commission));
+            let (tokens, commissions) = self.referral_totals.get(referrer).unwrap_or_default();
+            self.referral_totals.insert(
+                referrer,
+                &(
+                    tokens.saturating_add(amount),
+                    commissions.saturating_add(commission),
+                ),
+            );
+            self.escrowed = self.escrowed.saturating_add(commission);
+
+            self.env().emit_event(Referral {
+                referrer,
+                buyer: Self::env().caller(),
+                amount,
+                commission,
+            });
+        }
+
+        fn record_sale(&mut self, asset: PaymentAsset, price: u128, fee: u128) {
+            let (raised_price, raised_fee) = self.raised.get(asset).unwrap_or_default();
+            self.raised.insert(
+                asset,
+                &(
+                    raised_price.saturating_add(price),
+                    raised_fee.saturating_add(fee),
+                ),
+            );
+        }
+
+        /// Sends native funds out of the contract and books them against the recipient.
+        fn pay_out(&mut self, to: Address, amount: u128) -> Result<(), ink::env::Error> {
+            self.env().transfer(to, amount)?;
+            let withdrawn = self.withdrawn.get(to).unwrap_or_default();
+            self.withdrawn.insert(to, &withdrawn.saturating_add(amount));
+            Ok(())
+        }
+
+        #[ink(message)]
+        #[modifiers(only_owner)]
+        pub fn set_base_uri(&mut self, uri: PreludeString) -> Result<(), PSP34Error> {
+            self.base_uri = uri;
+            Ok(())
+        }
+
+        #[ink(message)]
+        #[modifiers(only_owner)]
+        pub fn set_mint_per_tx(&mut self, mint_per_tx: u128) -> Result<(), PSP34Error> {
+            self.mint_per_tx = mint_per_tx;
+            Ok(())
+        }
+
+        #[ink(message)]
+        #[modifiers(only_owner)]
+        pub fn set_mint_per_wallet(&mut self, mint_per_wallet: u128) -> Result<(), PSP34Error> {
+            self.mint_per_wallet = mint_per_wallet;
+            Ok(())
+        }
+
+        #[ink(message)]
+        #[modifiers(only_owner)]
+        pub fn set_mint_per_block(&mut self, mint_per_block: u128) -> Result<(), PSP34Error> {
+            self.mint_per_block = mint_per_block;
+            Ok(())
+        }
+
+        #[ink(message)]
+        #[modifiers(only_owner)]
+        pub fn set_purchase_cooldown(&mut self, purchase_cooldown: u64) -> Result<(), PSP34Error> {
+            self.purchase_cooldown = purchase_cooldown;
+            Ok(())
+        }
+
+        #[ink(message)]
+        #[modifiers(only_owner)]
+        pub fn set_referral_bps(&mut self, referral_bps: u128) -> Result<(), PSP34Error> {
+            if referral_bps > BPS_DENOMINATOR {
+                return Err(PSP34Error::Custom(String::from(
+                    "DropspaceSale::set_referral_bps: Commission can't exceed 100%",
+                )));
+            }
+            self.referral_bps = referral_bps;
+            Ok(())
+        }
+
+        /// Sets the collection whose holders can `holder_claim`, `None` turns claims off.
+        #[ink(message)]
+        #[modifiers(only_owner)]
+        pub fn set_holder_claim(
+            &mut self,
+            collection: Option<Address>,
+            price: u128,
+        ) -> Result<(), PSP34Error> {
+            self.holder_collection = collection;
+            self.holder_price = price;
+            Ok(())
+        }
+
+        /// Adds a tier and returns its id, tiers are numbered from zero in creation order.
+        #[ink(message)]
+        #[modifiers(only_owner)]
+        pub fn add_tier(
+            &mut self,
+            name: PreludeString,
+            supply_limit: u128,
+            price: u128,
+            fee: u128,
+            base_uri: PreludeString,
+        ) -> Result<u32, PSP34Error> {
+            let tier_id = self.tier_count;
+            self.tiers.insert(
+                tier_id,
+                &Tier {
+                    name,
+                    supply_limit,
+                    price,
+                    fee,
+                    base_uri,
+                    minted: 0,
+                },
+            );
+            self.tier_count = self.tier_count.saturating_add(1);
+            Ok(tier_id)
+        }
+
+        #[ink(message)]
+        #[modifiers(only_owner)]
+        pub fn update_tier(
+            &mut self,
+            tier_id: u32,
+            supply_limit: u128,
+            price: u128,
+            fee: u128,
+            base_uri: PreludeString,
+        ) -> Result<(), PSP34Error> {
+            let mut tier = self
+                .tiers
+                .get(tier_id)
+                .ok_or(PSP34Error::Custom(String::from(
+                    "DropspaceSale::update_tier: Tier not found",
+                )))?;
+            if supply_limit < tier.minted {
+                return Err(PSP34Error::Custom(String::from(
+                    "DropspaceSale::update_tier: Supply limit below minted",
+                )));
+            }
+            tier.supply_limit = supply_limit;
+            tier.price = price;
+            tier.fee = fee;
+            tier.base_uri = base_uri;
+            self.tiers.insert(tier_id, &tier);
+            Ok(())
+        }
+
+        /// Sets the mint pass collection for `redeem` and the window it's open in.
+        #[ink(message)]
+        #[modifiers(only_owner)]
+        pub fn set_redemption(
+            &mut self,
+            collection: Option<Address>,
+            start: u64,
+            end: u64,
+        ) -> Result<(), PSP34Error> {
+            if start > end {
+                return Err(PSP34Error::Custom(String::from(
+                    "DropspaceSale::set_redemption: Window ends before it starts",
+                )));
+            }
+            self.pass_collection = collection;
+            self.redemption_start = start;
+            self.redemption_end = end;
+            Ok(())
+        }
+
+        #[ink(message)]
+        #[modifiers(only_owner)]
+        pub fn set_mint_price(&mut self, mint_price: u128) -> Result<(), PSP34Error> {
+            let (_, mint_fee) = self.payment_price_of(PaymentAsset::Native)?;
+            self.payment_prices
+                .insert(PaymentAsset::Native, &(mint_price, mint_fee));
+            Ok(())
+        }
+
+        #[ink(message)]
+        #[modifiers(only_owner)]
+        pub fn set_sale_time(&mut self, sale_time: u64) -> Result<(), PSP34Error> {
+            self.sale_time = sale_time;
+            Ok(())
+        }
+
+        #[ink(message)]
+        #[modifiers(only_owner)]
+        pub fn toggle_sale_active(&mut self) -> Result<(), PSP34Error> {
+            if self.sale_time() != 0 {
+                self.sale_time = 0;
+            } else {
+                self.sale_time = u64::MAX;
+            }
+            Ok(())
+        }
+
+        #[ink(message)]
+        #[modifiers(only_owner)]
+        pub fn pause(&mut self) -> Result<(), PSP34Error> {
+            pausable::Internal::_pause(self)?;
+            Ok(())
+        }
+
+        #[ink(message)]
+        #[modifiers(only_owner)]
+        pub fn unpause(&mut self) -> Result<(), PSP34Error> {
+            pausable::Internal::_unpause(self)?;
+            Ok(())
+        }
+
+        #[ink(message)]
+        #[modifiers(only_owner)]
+        pub fn set_pause_transfers(&mut self, pause_transfers: bool) -> Result<(), PSP34Error> {
+            self.pause_transfers = pause_transfers;
+            Ok(())
+        }
+
+        #[ink(message)]
+        #[modifiers(only_owner)]
+        pub fn set_eoa_only(&mut self, eoa_only: bool) -> Result<(), PSP34Error> {
+            self.eoa_only = eoa_only;
+            Ok(())
+        }
+
+        /// Lets a contract caller, e.g. a checkout relayer, buy while `eoa_only` is on.
+        #[ink(message)]
+        #[modifiers(only_owner)]
+        pub fn add_trusted_caller(&mut self, caller: Address) -> Result<(), PSP34Error> {
+            self.trusted_callers.insert(caller, &());
+            Ok(())
+        }
+
+        #[ink(message)]
+        #[modifiers(only_owner)]
+        pub fn remove_trusted_caller(&mut self, caller: Address) -> Result<(), PSP34Error> {
+            self.trusted_callers.remove(caller);
+            Ok(())
+        }
+
+        /// Sets how the native price moves with supply, `mint_price` stays the starting price.
+        #[ink(message)]
+        #[modifiers(only_owner)]
+        pub fn set_pricing(&mut self, pricing: PricingModel) -> Result<(), PSP34Error> {
+            if let PricingModel::Step { step_size: 0, .. } = pricing {
+                return Err(PSP34Error::Custom(String::from(
+                    "DropspaceSale::set_pricing: Step size can't be zero",
+                )));
+            }
+            self.pricing = pricing;
+            Ok(())
+        }
+
+        /// Sets the bulk discount table of `(min quantity, basis points)`, the largest discount a
+        /// purchase qualifies for is taken off the price, and off the fee if `discount_fee`.
+        #[ink(message)]
+        #[modifiers(only_owner)]
+        pub fn set_bulk_discounts(
+            &mut self,
+            bulk_discounts: PreludeVec<(u128, u128)>,
+            discount_fee: bool,
+        ) -> Result<(), PSP34Error> {
+            if bulk_discounts.iter().any(|(_, bps)| *bps > BPS_DENOMINATOR) {
+                return Err(PSP34Error::Custom(String::from(
+                    "DropspaceSale::set_bulk_discounts: Discount can't exceed 100%",
+                )));
+            }
+            self.bulk_discounts = bulk_discounts;
+            self.discount_fee = discount_fee;
+            Ok(())
+        }
+
+        /// Registers a promo code by its Blake2x256 hash, the code itself never goes on chain
+        /// until someone buys with it. The discount applies to the fee too if `discount_fee`.
+        #[ink(message)]
+        #[modifiers(only_owner)]
+        pub fn add_promo_code(
+            &mut self,
+            code_hash: [u8; 32],
+            discount_bps: u128,
+            discount_fee: bool,
+            max_uses: u32,
+            expires_at: u64,
+        ) -> Result<(), PSP34Error> {
+            if discount_bps > BPS_DENOMINATOR {
+                return Err(PSP34Error::Custom(String::from(
+                    "DropspaceSale::add_promo_code: Discount can't exceed 100%",
+                )));
+            }
+            let uses = self
+                .promo_codes
+                .get(code_hash)
+                .map(|promo| promo.uses)
+                .unwrap_or_default();
+            self.promo_codes.insert(
+                code_hash,
+                &PromoCode {
+                    discount_bps,
+                    discount_fee,
+                    max_uses,
+                    uses,
+                    expires_at,
+                },
+            );
+            Ok(())
+        }
+
+        #[ink(message)]
+        #[modifiers(only_owner)]
+        pub fn remove_promo_code(&mut self, code_hash: [u8; 32]) -> Result<(), PSP34Error> {
+            if !self.promo_codes.contains(code_hash) {
+                return Err(PSP34Error::Custom(String::from(
+                    "DropspaceSale::remove_promo_code: Promo code not found",
+                )));
+            }
+            self.promo_codes.remove(code_hash);
+            Ok(())
+        }
+
+        #[ink(message)]
+        #[modifiers(only_owner)]
+        pub fn add_payment_asset(
+            &mut self,
+            asset: PaymentAsset,
+            price: u128,
+            fee: u128,
+        ) -> Result<(), PSP34Error> {
+            if !self.payment_prices.contains(asset) {
+                self.payment_assets.push(asset);
+            }
+            self.payment_prices.insert(asset, &(price, fee));
+            Ok(())
+        }
+
+        #[ink(message)]
+        #[modifiers(only_owner)]
+        pub fn remove_payment_asset(&mut self, asset: PaymentAsset) -> Result<(), PSP34Error> {
+            if asset == PaymentAsset::Native {
+                return Err(PSP34Error::Custom(String::from(
+                    "DropspaceSale::remove_payment_asset: Native currency can't be removed",
+                )));
+            }
+            if !self.payment_prices.contains(asset) {
+                return Err(PSP34Error::Custom(String::from(
+                    "DropspaceSale::remove_payment_asset: Payment asset not accepted",
+                )));
+            }
+            self.payment_prices.remove(asset);
+            self.payment_assets.retain(|accepted| *accepted != asset);
+            Ok(())
+        }
+
+        #[ink(message)]
+        #[modifiers(only_owner)]
+        pub fn set_supply_limit(&mut self, supply_limit: u128) -> Result<(), PSP34Error> {
+            let current_supply: u128 = self.allocated_supply();
+            if current_supply > supply_limit {
+                return Err(PSP34Error::Custom(String::from(
+                    "DropspaceSale::set_total_supply: Supply limit is lesser than current supply",
+                )));
+            }
+            self.supply_limit = supply_limit;
+            Ok(())
+        }
+
+        #[ink(message)]
+        #[modifiers(only_owner)]
+        pub fn update_sale_config(&mut self, update: SaleConfigUpdate) -> Result<(), PSP34Error> {
+            // Validate everything first so the update is applied all-or-nothing
+            let current_supply: u128 = self.allocated_supply();
+            if let Some(supply_limit) = update.supply_limit {
+                if current_supply > supply_limit {
+                    return Err(PSP34Error::Custom(String::from(
+                        "DropspaceSale::update_sale_config: Supply limit is lesser than current supply",
+                    )));
+                }
+            }
+            let (mint_price, mint_fee) = self.payment_price_of(PaymentAsset::Native)?;
+
+            if let Some(base_uri) = update.base_uri {
+                self.base_uri = base_uri;
+            }
+            if let Some(supply_limit) = update.supply_limit {
+                self.supply_limit = supply_limit;
+            }
+            if let Some(mint_per_tx) = update.mint_per_tx {
+                self.mint_per_tx = mint_per_tx;
+            }
+            if let Some(mint_per_wallet) = update.mint_per_wallet {
+                self.mint_per_wallet = mint_per_wallet;
+            }
+            if let Some(mint_per_block) = update.mint_per_block {
+                self.mint_per_block = mint_per_block;
+            }
+            if let Some(purchase_cooldown) = update.purchase_cooldown {
+                self.purchase_cooldown = purchase_cooldown;
+            }
+            if update.mint_price.is_some() || update.mint_fee.is_some() {
+                self.payment_prices.insert(
+                    PaymentAsset::Native,
+                    &(
+                        update.mint_price.unwrap_or(mint_price),
+                        update.mint_fee.unwrap_or(mint_fee),
+                    ),
+                );
+            }
+            if let Some(withdraw_wallet) = update.withdraw_wallet {
+                self.withdraw_wallet = withdraw_wallet;
+            }
+            if let Some(sale_time) = update.sale_time {
+                self.sale_time = sale_time;
+            }
+            if let Some(pause_transfers) = update.pause_transfers {
+                self.pause_transfers = pause_transfers;
+            }
+            if let Some(eoa_only) = update.eoa_only {
+                self.eoa_only = eoa_only;
+            }
+            Ok(())
+        }
+
+        #[ink(message)]
+        #[modifiers(only_owner)]
+        pub fn set_withdraw_wallet(
+            &mut self,
+            withdraw_wallet: Option<Address>,
+        ) -> Result<(), PSP34Error> {
+            self.withdraw_wallet = withdraw_wallet;
+            Ok(())
+        }
+
+        #[ink(message)]
+        pub fn token_uri(&self, token_id: u128) -> Result<PreludeString, PSP34Error> {
+            let base_uri = match self
+                .token_tiers
+                .get(token_id)
+                .and_then(|tier_id| self.tiers.get(tier_id))
+            {
+                Some(tier) => tier.base_uri,
+                None => self.base_uri.clone(),
+            };
+            Ok(format!("{base_uri}{token_id}"))
+        }
+
+        #[ink(message)]
+        pub fn tier(&self, tier_id: u32) -> Option<Tier> {
+            self.tiers.get(tier_id)
+        }
+
+        #[ink(message)]
+        pub fn tier_count(&self) -> u32 {
+            self.tier_count
+        }
+
+        #[ink(message)]
+        pub fn token_tier(&self, token_id: u128) -> Option<u32> {
+            self.token_tiers.get(token_id)
+        }
+
+        #[ink(message)]
+        pub fn supply_limit(&self) -> u128 {
+            self.supply_limit
+        }
+
+        #[ink(message)]
+        pub fn mint_per_tx(&self) -> u128 {
+            self.mint_per_tx
+        }
+
+        #[ink(message)]
+        pub fn mint_per_wallet(&self) -> u128 {
+            self.mint_per_wallet
+        }
+
+        #[ink(message)]
+        pub fn mint_per_block(&self) -> u128 {
+            self.mint_per_block
+        }
+
+        #[ink(message)]
+        pub fn purchase_cooldown(&self) -> u64 {
+            self.purchase_cooldown
+        }
+
+        #[ink(message)]
+        pub fn last_purchase(&self, account: Address) -> Option<u64> {
+            self.last_purchase.get(account)
+        }
+
+        #[ink(message)]
+        pub fn minted(&self, account: Address) -> u128 {
+            self.minted.get(account).unwrap_or_default()
+        }
+
+        #[ink(message)]
+        pub fn get_account_balance(&self) -> u128 {
+            self.env().balance()
+        }
+
+        #[ink(message)]
+        pub fn mint_price(&self) -> u128 {
+            self.payment_price(PaymentAsset::Native)
+                .unwrap_or_default()
+                .0
+        }
+
+        #[ink(message)]
+        pub fn mint_fee(&self) -> u128 {
+            self.payment_price(PaymentAsset::Native)
+                .unwrap_or_default()
+                .1
+        }
+
+        #[ink(message)]
+        pub fn payment_assets(&self) -> PreludeVec<PaymentAsset> {
+            self.payment_assets.clone()
+        }
+
+        #[ink(message)]
+        pub fn payment_price(&self, asset: PaymentAsset) -> Option<(u128, u128)> {
+            self.payment_prices.get(asset)
+        }
+
+        #[ink(message)]
+        pub fn pricing(&self) -> PricingModel {
+            self.pricing
+        }
+
+        #[ink(message)]
+        pub fn bulk_discounts(&self) -> PreludeVec<(u128, u128)> {
+            self.bulk_discounts.clone()
+        }
+
+        #[ink(message)]
+        pub fn discount_fee(&self) -> bool {
+            self.discount_fee
+        }
+
+        #[ink(message)]
+        pub fn promo_code(&self, code_hash: [u8; 32]) -> Option<PromoCode> {
+            self.promo_codes.get(code_hash)
+        }
+
+        #[ink(message)]
+        pub fn quote(&self, asset: PaymentAsset, amount: u128, buyer: Address) -> Quote {
+            match self.checkout(asset, buyer, buyer, amount) {
+                Ok((price, fee, discount)) => Quote {
+                    price,
+                    fee,
+                    total: price.saturating_add(fee),
+                    discount,
+                    breakdown: self.price_breakdown(asset, amount).unwrap_or_default(),
+                    rejection: None,
+                },
+                Err(error) => Quote {
+                    rejection: Some(error),
+                    ..Default::default()
+                },
+            }
+        }
+
+        #[ink(message)]
+        pub fn dev_wallet(&self) -> Option<Address> {
+            self.dev_wallet
+        }
+
+        #[ink(message)]
+        pub fn withdraw_wallet(&self) -> Option<Address> {
+            self.withdraw_wallet
+        }
+
+        #[ink(message)]
+        pub fn sale_time(&self) -> u64 {
+            self.sale_time
+        }
+
+        #[ink(message)]
+        pub fn sale_end_time(&self) -> u64 {
+            self.sale_end_time
+        }
+
+        #[ink(message)]
+        pub fn funding_target(&self) -> u128 {
+            self.funding_target
+        }
+
+        #[ink(message)]
+        pub fn min_sales(&self) -> u128 {
+            self.min_sales
+        }
+
+        /// Whether held crowdfund sales meet both the price target and the minimum sale count.
+        #[ink(message)]
+        pub fn funding_reached(&self) -> bool {
+            self.crowdfund_held.0 >= self.funding_target && self.crowdfund_sold >= self.min_sales
+        }
+
+        #[ink(message)]
+        pub fn funds_released(&self) -> bool {
+            self.funds_released
+        }
+
+        #[ink(message)]
+        pub fn token_payment(&self, token_id: u128) -> Option<Payment> {
+            self.token_payments.get(token_id)
+        }
+
+        #[ink(message)]
+        pub fn refund_window(&self) -> u64 {
+            self.refund_window
+        }
+
+        #[ink(message)]
+        pub fn refund_fee(&self) -> bool {
+            self.refund_fee
+        }
+
+        #[ink(message)]
+        pub fn refund_reserve(&self) -> (u128, u128) {
+            self.refund_reserve
+        }
+
+        #[ink(message)]
+        pub fn refund_window_end(&self) -> u64 {
+            self.refund_window_end
+        }
+
+        #[ink(message)]
+        pub fn sale_active(&self) -> bool {
+            let now = self.env().block_timestamp();
+            self.sale_time <= now && (self.sale_end_time == 0 || now < self.sale_end_time)
+        }
+
+        #[ink(message)]
+        pub fn base_uri(&self) -> PreludeString {
+            self.base_uri.clone()
+        }
+
+        #[ink(message)]
+        pub fn pause_transfers(&self) -> bool {
+            self.pause_transfers
+        }
+
+        #[ink(message)]
+        pub fn referral_bps(&self) -> u128 {
+            self.referral_bps
+        }
+
+        #[ink(message)]
+        pub fn referral_balance(&self, referrer: Address) -> u128 {
+            self.referral_balances.get(referrer).unwrap_or_default()
+        }
+
+        /// Tokens sold and commission earned through `referrer`, over the whole sale.
+        #[ink(message)]
+        pub fn referral_totals(&self, referrer: Address) -> (u128, u128) {
+            self.referral_totals.get(referrer).unwrap_or_default()
+        }
+
+        #[ink(message)]
+        pub fn holder_collection(&self) -> Option<Address> {
+            self.holder_collection
+        }
+
+        #[ink(message)]
+        pub fn holder_price(&self) -> u128 {
+            self.holder_price
+        }
+
+        #[ink(message)]
+        pub fn holder_claimed(&self, id: Id) -> bool {
+            self.holder_collection
+                .map(|collection| self.holder_claims.contains((collection, id)))
+                .unwrap_or(false)
+        }
+
+        #[ink(message)]
+        pub fn pass_collection(&self) -> Option<Address> {
+            self.pass_collection
+        }
+
+        #[ink(message)]
+        pub fn redemption_window(&self) -> (u64, u64) {
+            (self.redemption_start, self.redemption_end)
+        }
+
+        #[ink(message)]
+        pub fn pass_redeemed(&self, collection: Address, id: Id) -> bool {
+            self.redeemed_passes.contains((collection, id))
+        }
+
+        #[ink(message)]
+        pub fn eoa_only(&self) -> bool {
+            self.eoa_only
+        }
+
+        #[ink(message)]
+        pub fn is_trusted_caller(&self, caller: Address) -> bool {
+            self.trusted_callers.contains(caller)
+        }
+
+        #[ink(message)]
+        pub fn tokens_of_owner(&self, owner: Address, offset: u128, limit: u128) -> PreludeVec<Id> {
+            let balance = psp34::PSP34::balance_of(self, owner) as u128;
+            let end = offset.saturating_add(limit).min(balance);
+            (offset..end)
+                .filter_map(|index| PSP34Enumerable::owners_token_by_index(self, owner, index).ok())
+                .collect()
+        }
+
+        #[ink(message)]
+        pub fn raffle(&self) -> Option<Raffle> {
+            self.raffle.clone()
+        }
+
+        #[ink(message)]
+        pub fn raffle_entry(&self, entrant: Address) -> RaffleEntry {
+            self.raffle_entries.get(entrant).unwrap_or_default()
+        }
+
+        #[ink(message)]
+        pub fn auction(&self, id: Id) -> Option<Auction> {
+            self.auctions.get(id)
+        }
+
+        #[ink(message)]
+        pub fn sale_stats(&self) -> SaleStats {
+            self.stats.clone()
+        }
+
+        #[ink(message)]
+        pub fn raised(&self, asset: PaymentAsset) -> (u128, u128) {
+            self.raised.get(asset).unwrap_or_default()
+        }
+
+        #[ink(message)]
+        pub fn refunded(&self, asset: PaymentAsset) -> u128 {
+            self.refunded.get(asset).unwrap_or_default()
+        }
+
+        #[ink(message)]
+        pub fn withdrawn(&self, recipient: Address) -> u128 {
+            self.withdrawn.get(recipient).unwrap_or_default()
+        }
+
+        #[ink(message)]
+        pub fn sale_info(&self) -> SaleInfo {
+            SaleInfo {
+                base_uri: self.base_uri(),
+                supply_limit: self.supply_limit,
+                mint_per_tx: self.mint_per_tx,
+                mint_per_wallet: self.mint_per_wallet,
+                mint_per_block: self.mint_per_block,
+                purchase_cooldown: self.purchase_cooldown,
+                mint_price: self.mint_price(),
+                mint_fee: self.mint_fee(),
+                withdraw_wallet: self.withdraw_wallet,
+                dev_wallet: self.dev_wallet,
+                sale_time: self.sale_time,
+                sale_end_time: self.sale_end_time,
+                sale_active: self.sale_active(),
+                paused: pausable::Internal::_paused(self),
+                pause_transfers: self.pause_transfers,
+                eoa_only: self.eoa_only,
+                total_supply: psp34::PSP34::total_supply(self),
+                block_timestamp: self.env().block_timestamp(),
+            }
+        }
+
+        #[ink(message)]
+        #[modifiers(only_owner, non_reentrant)]
+        pub fn withdraw(&mut self) -> Result<(), PSP34Error> {
+            let withdraw_wallet = self
+                .withdraw_wallet
+                .ok_or(PSP34Error::Custom(String::from("Owner wallet not set")))?;
+            let withdrawable = self.withdrawable();
+
+            if withdrawable > 0 {
+                match self.pay_out(withdraw_wallet, withdrawable) {
+                    Ok(_) => Ok(()),
+                    Err(_) => Err(PSP34Error::Custom(String::from("Withdrawal failed"))),
+                }
+            } else {
+                Err(PSP34Error::Custom(String::from("No funds to withdraw")))
+            }
+        }
+
+        #[ink(message)]
+        #[modifiers(only_owner, non_reentrant)]
+        pub fn withdraw_to(&mut self, recipient: Address, amount: u128) -> Result<(), PSP34Error> {
+            if amount > self.withdrawable() {
+                return Err(PSP34Error::Custom(String::from(
+                    "DropspaceSale::withdraw_to: Amount exceeds withdrawable balance",
+                )));
+            }
+
+            match self.pay_out(recipient, amount) {
+                Ok(_) => Ok(()),
+                Err(_) => Err(PSP34Error::Custom(String::from("Withdrawal failed"))),
+            }
+        }
+
+        #[ink(message)]
+        #[modifiers(only_owner, non_reentrant)]
+        pub fn rescue_psp22(
+            &mut self,
+            token: Address,
+            to: Address,
+            amount: u128,
+        ) -> Result<(), PSP34Error> {
+            let mut token: PSP22Ref = token.into();
+            token.transfer(to, amount, PreludeVec::new()).map_err(|_| {
+                PSP34Error::Custom(String::from("DropspaceSale::rescue_psp22: Transfer failed"))
+            })
+        }
+
+        #[ink(message)]
+        #[modifiers(only_owner, non_reentrant)]
+        pub fn rescue_psp34(
+            &mut self,
+            collection: Address,
+            id: Id,
+            to: Address,
+        ) -> Result<(), PSP34Error> {
+            let contract = self.env().account_id();
+
+            if self.redeemed_passes.contains((collection, id.clone())) {
+                return Err(PSP34Error::Custom(String::from(
+                    "DropspaceSale::rescue_psp34: Token is a redeemed pass",
+                )));
+            }
+
+            if collection != contract {
+                let mut collection: PSP34Ref = collection.into();
+                return collection.transfer(to, id, PreludeVec::new()).map_err(|_| {
+                    PSP34Error::Custom(String::from("DropspaceSale::rescue_psp34: Transfer failed"))
+                });
+            }
+
+            if self.pause_transfers && pausable::Internal::_paused(self) {
+                return Err(PSP34Error::Custom(String::from(
+                    "DropspaceSale::rescue_psp34: Transfers are paused",
+                )));
+            }
+            if self
+                .auctions
+                .get(id.clone())
+                .map_or(false, |auction| !auction.settled)
+            {
+                return Err(PSP34Error::Custom(String::from(
+                    "DropspaceSale::rescue_psp34: Token is under auction",
+                )));
+            }
+            // Whoever receives a refundable token could claim its refund
+            if let Id::U128(token_id) = id {
+                if let Some(payment) = self.token_payments.get(token_id) {
+                    if (self.crowdfund && !self.funds_released)
+                        || self.env().block_timestamp() < payment.refundable_until
+                    {
+                        return Err(PSP34Error::Custom(String::from(
+                            "DropspaceSale::rescue_psp34: Token still refundable",
+                        )));
+                    }
+                }
+            }
+            if psp34::PSP34::owner_of(self, id.clone()) != Some(contract) {
+                return Err(PSP34Error::Custom(String::from(
+                    "DropspaceSale::rescue_psp34: Token not held by contract",
+                )));
+            }
+
+            // The contract can't approve itself for its own tokens, so the id is re-minted
+            // to the recipient instead. Supply and the token id stay the same.
+            psp34::Internal::_burn_from(self, contract, id.clone())?;
+            psp34::Internal::_mint_to(self, to, id)
+        }
+
+        #[ink(message)]
+        #[modifiers(non_reentrant)]
+        pub fn claim_referral_rewards(&mut self) -> Result<(), PSP34Error> {
+            let referrer = Self::env().caller();
+            let balance = self.referral_balances.get(referrer).unwrap_or_default();
+            if balance == 0 {
+                return Err(PSP34Error::Custom(String::from(
+                    "DropspaceSale::claim_referral_rewards: Nothing to claim",
+                )));
+            }
+
+            self.referral_balances.remove(referrer);
+            self.escrowed = self.escrowed.saturating_sub(balance);
+            self.pay_out(referrer, balance)
+                .map_err(|_| PSP34Error::Custom(String::from("Transfer to referrer failed")))
+        }
+
+        /// Balance the owner can take out, the existential deposit and escrowed refunds stay put.
+        #[ink(message)]
+        pub fn withdrawable(&self) -> u128 {
+            self.env()
+                .balance()
+                .saturating_sub(self.env().minimum_balance())
+                .saturating_sub(self.escrowed)
+        }
+
+        #[ink(message)]
+        pub fn escrowed(&self) -> u128 {
+            self.escrowed
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    #[rustfmt::skip]
+    use super::*;
+    use dropspace_sale::{
+        Contract, Payment, PaymentAsset, PricingModel, SaleConfigUpdate, SaleStats,
+        BPS_DENOMINATOR, MAX_AIRDROP_PER_CALL,
+    };
+    use ink::env::hash::{Blake2x256, HashOutput};
+    use ink::{env::DefaultEnvironment as Environment, primitives::AccountId};
+    use openbrush::contracts::ownable::Ownable;
+    use openbrush::contracts::pausable::Pausable;
+    use openbrush::contracts::psp34::extensions::enumerable::psp34enumerable_external::PSP34Enumerable;
+    use openbrush::contracts::psp34::extensions::metadata::psp34metadata_external::PSP34Metadata;
+    use openbrush::contracts::psp34::{psp34, Id, PSP34Error};
+    use openbrush::contracts::reentrancy_guard::*;
+    use openbrush::traits::Storage;
+
+    fn default_accounts() -> ink::env::test::DefaultAccounts<ink::env::DefaultEnvironment> {
+        ink::env::test::default_accounts::<ink::env::DefaultEnvironment>()
+    }
+
+    struct ContractParam {
+        name: String,
+        symbol: String,
+        base_uri: String,
+        supply_limit: u128,
+        mint_per_tx: u128,
+        mint_price: u128,
+        mint_fee: u128,
+        withdraw_wallet: Option<AccountId>,
+        dev_wallet: Option<AccountId>,
+        sale_time: u64,
+    }
+
+    impl Default for ContractParam {
+        fn default() -> ContractParam {
+            ContractParam {
+                name: "Test".to_string(),
+                symbol: "TST".to_string(),
+                base_uri: "https://example.com/token/".to_string(),
+                supply_limit: 100000,
+                mint_per_tx: 10,
+                mint_price: 1000,
+                mint_fee: 10,
+                withdraw_wallet: None,
+                dev_wallet: None,
+                sale_time: 0,
+            }
+        }
+    }
+
+    fn get_contract(args: &ContractParam) -> Contract {
+        return Contract::new(
+            args.name.to_string(),
+            args.symbol.to_string(),
+            args.base_uri.to_string(),
+            args.mint_per_tx,
+            args.mint_price,
+            args.mint_fee,
+            args.supply_limit,
+            args.withdraw_wallet,
+            args.dev_wallet,
+            args.sale_time,
+        );
+    }
+
+    #[ink::test]
+    fn new_works() {
+        let accounts = default_accounts();
+
+        let params = ContractParam {
+            withdraw_wallet: Some(accounts.django),
+            dev_wallet: Some(accounts.alice),
+            ..Default::default()
+        };
+        let contract = get_contract(&params);
+
+        assert_eq!(contract.supply_limit(), params.supply_limit);
+        assert_eq!(contract.mint_per_tx(), params.mint_per_tx);
+        assert_eq!(contract.mint_price(), params.mint_price);
+        assert_eq!(contract.mint_fee(), params.mint_fee);
+        assert_eq!(contract.dev_wallet(), params.dev_wallet);
+        assert_eq!(contract.withdraw_wallet(), params.withdraw_wallet);
+        assert_eq!(contract.sale_time(), params.sale_time);
+        assert_eq!(contract.sale_active(), true);
+
+        let id = psp34::PSP34::collection_id(&contract);
+        assert_eq!(
+            PSP34Metadata::get_attribute(&contract, id.clone(), String::from("name")),
+            Some(params.name)
+        );
+        assert_eq!(
+            PSP34Metadata::get_attribute(&contract, id.clone(), String::from("symbol")),
+            Some(params.symbol)
+        );
+        assert_eq!(contract.base_uri(), params.base_uri);
+    }
+
+    #[ink::test]
+    fn reserve_works() {
+        let accounts = default_accounts();
+        let mut contract = Contract::new(
+            "Test".to_string(),
+            "TST".to_string(),
+            "https://example.com/token/".to_string(),
+            10,
+            1000,
+            10,
+            100000,
+            Some(accounts.django),
+            Some(accounts.alice),
+            12345678,
+        );
+
+        assert_eq!(contract.reserve(5), Ok(()));
+        assert_eq!(psp34::PSP34::total_supply(&contract), 5);
+        assert_eq!(
+            contract.reserve(100001),
+            Err(PSP34Error::Custom(String::from(
+                "DropspaceSale::reserve: Supply limit reached"
+            )))
+        );
+    }
+
+    #[ink::test]
+    fn buy_works() {
+        let accounts = default_accounts();
+
+        // Set owner
+        ink::env::test::set_caller::<ink::env::DefaultEnvironment>(accounts.charlie);
+
+        let params = ContractParam {
+            withdraw_wallet: Some(accounts.django),
+            dev_wallet: Some(accounts.eve),
+            ..Default::default()
+        };
+        let mut contract = get_contract(&params);
+        assert_eq!(contract.get_account_balance(), 1000000);
+
+        // current owner checking
+        assert_eq!(Ownable::owner(&contract), Some(accounts.charlie));
+
+        ink::env::debug_println!(" Dev Fee={:?}", contract.mint_fee());
+        ink::env::debug_println!(" Contract balance={:?}\n", contract.get_account_balance());
+
+        ink::env::test::set_account_balance::<ink::env::DefaultEnvironment>(
+            accounts.bob,
+            100_000_000,
+        );
+        ink::env::test::set_account_balance::<ink::env::DefaultEnvironment>(accounts.django, 0);
+        ink::env::test::set_account_balance::<ink::env::DefaultEnvironment>(accounts.eve, 0);
+        ink::env::debug_println!(
+            " Withdraw Wallet (django) bal={:?}",
+            ink::env::test::get_account_balance::<ink::env::DefaultEnvironment>(accounts.django)
+        );
+        ink::env::debug_println!(
+            " Dev Wallet (eve) bal={:?}",
+            ink::env::test::get_account_balance::<ink::env::DefaultEnvironment>(accounts.eve)
+        );
+
+        let qty = 7;
+        let required_value = qty * (params.mint_price + params.mint_fee);
+
+        // Setting the caller for the next contract call
+        ink::env::test::set_caller::<ink::env::DefaultEnvironment>(accounts.bob);
+
+        assert_eq!(
+            ink::env::pay_with_call!(contract.buy(qty), required_value),
+            Ok(())
+        );
+
+        assert_eq!(psp34::PSP34::total_supply(&contract), qty);
+
+        assert_eq!(
+            psp34::PSP34::owner_of(&contract, Id::U128(1)),
+            Some(accounts.bob)
+        );
+        assert_eq!(psp34::PSP34::owner_of(&contract, Id::U128(qty)), None); // invalid token's ownership test
+
+        // Checking splitted balances
+        ink::env::debug_println!(
+            "\n Withdraw Wallet (django) bal={:?}",
+            ink::env::test::get_account_balance::<ink::env::DefaultEnvironment>(accounts.django)
+        );
+        ink::env::debug_println!(
+            " Dev Wallet (chalie) bal={:?}",
+            ink::env::test::get_account_balance::<ink::env::DefaultEnvironment>(accounts.eve)
+        );
+        ink::env::debug_println!(
+            " User Wallet (bob) bal={:?}",
+            ink::env::test::get_account_balance::<ink::env::DefaultEnvironment>(accounts.bob)
+        );
+        assert_eq!(
+            ink::env::test::get_account_balance::<ink::env::DefaultEnvironment>(accounts.bob),
+            Ok(100_000_000 - required_value)
+        );
+        assert_eq!(
+            ink::env::test::get_account_balance::<ink::env::DefaultEnvironment>(accounts.django),
+            Ok(qty * params.mint_price)
+        );
+
+        let __dev_bal: Result<u128, ink::env::Error> =
+            ink::env::test::get_account_balance::<ink::env::DefaultEnvironment>(accounts.eve);
+        assert_eq!(__dev_bal, Ok(qty * params.mint_fee));
+        ink::env::debug_println!(" Dev Wallet (eve) bal={:?}", __dev_bal);
+        ink::env::debug_println!(" Contract balance={:?}\n", contract.get_account_balance());
+
+        // Transfer test
+        assert_eq!(
+            psp34::PSP34::owner_of(&contract, Id::U128(0)),
+            Some(accounts.bob)
+        );
+
+        assert_eq!(
+            psp34::PSP34::transfer(&mut contract, accounts.frank, Id::U128(0), Vec::new()),
+            Ok(())
+        );
+
+        assert_eq!(
+            psp34::PSP34::owner_of(&contract, Id::U128(0)),
+            Some(accounts.frank)
+        );
+
+        // Invalid Buy
+        assert_eq!(
+            contract.buy(100001),
+            Err(PSP34Error::Custom(String::from(
+                "DropspaceSale::buy: Supply limit reached"
+            )))
+        );
+        assert_eq!(
+            contract.buy(15),
+            Err(PSP34Error::Custom(String::from(
+                "DropspaceSale::buy: Can't exceed amount of mints per tx"
+            )))
+        );
+        assert_eq!(
+            ink::env::pay_with_call!(
+                contract.buy(4),
+                4 * (params.mint_fee + params.mint_price) - 1
+            ),
+            Err(PSP34Error::Custom(String::from(
+                "DropspaceSale::buy: Wrong amount paid."
+            )))
+        );
+    }
+
+    #[ink::test]
+    fn buy2_works() {
+        let accounts = default_accounts();
+
+        // Set owner
+        ink::env::test::set_caller::<ink::env::DefaultEnvironment>(accounts.charlie);
+
+        let params = ContractParam {
+            mint_fee: 0,
+            withdraw_wallet: Some(accounts.django),
+            dev_wallet: Some(accounts.eve),
+            ..Default::default()
+        };
+        let mut contract = get_contract(&params);
+
+        ink::env::test::set_caller::<ink::env::DefaultEnvironment>(accounts.bob);
+        assert_eq!(
+            ink::env::pay_with_call!(contract.buy(1), params.mint_price),
+            Ok(())
+        );
+
+        assert_eq!(psp34::PSP34::total_supply(&contract), 1);
+    }
+
+    #[ink::test]
+    fn setters_work() {
+        let accounts = default_accounts();
+
+        // Set owner
+        ink::env::test::set_caller::<ink::env::DefaultEnvironment>(accounts.charlie);
+
+        let params = ContractParam {
+            withdraw_wallet: Some(accounts.django),
+            dev_wallet: Some(accounts.alice),
+            ..Default::default()
+        };
+        let mut contract = get_contract(&params);
+
+        assert_eq!(
+            contract.set_base_uri("https://newuri.com/token/".to_string()),
+            Ok(())
+        );
+        assert_eq!(contract.set_mint_per_tx(20), Ok(()));
+        assert_eq!(contract.set_mint_price(2000), Ok(()));
+        assert_eq!(contract.set_sale_time(87654321), Ok(()));
+        assert_eq!(contract.set_supply_limit(50000), Ok(()));
+        assert_eq!(contract.reserve(5), Ok(()));
+        assert_eq!(
+            contract.set_supply_limit(1),
+            Err(PSP34Error::Custom(String::from(
+                "DropspaceSale::set_total_supply: Supply limit is lesser than current supply"
+            )))
+        );
+    }
+
+    #[ink::test]
+    fn sale_active_works() {
+        let accounts = default_accounts();
+        let params = ContractParam {
+            withdraw_wallet: Some(accounts.django),
+            dev_wallet: Some(accounts.alice),
+            ..Default::default()
+        };
+        let mut contract = get_contract(&params);
+
+        // Set the block timestamp to simulate sale time passing
+        ink::env::test::set_block_timestamp::<ink::env::DefaultEnvironment>(12345678);
+        assert_eq!(contract.sale_active(), true);
+
+        let __ = contract.set_sale_time(12345679);
+
+        // After the sale time, sale should be active
+        assert_eq!(contract.sale_active(), false);
+
+        let __ = contract.set_sale_time(0);
+        assert_eq!(contract.sale_active(), true);
+    }
+
+    #[ink::test]
+    fn toggle_sale_active_works() {
+        let accounts = default_accounts();
+        let params = ContractParam {
+            withdraw_wallet: Some(accounts.django),
+            dev_wallet: Some(accounts.alice),
+            ..Default::default()
+        };
+
+        // Set owner
+        ink::env::test::set_caller::<ink::env::DefaultEnvironment>(accounts.charlie);
+
+        let mut contract = get_contract(&params);
+
+        // Ensure that only the owner can toggle sale active
+        ink::env::test::set_caller::<ink::env::DefaultEnvironment>(accounts.bob);
+        assert_eq!(
+            contract.toggle_sale_active(),
+            Err(PSP34Error::Custom(String::from("O::CallerIsNotOwner")))
+        );
+
+        assert_eq!(contract.sale_active(), true);
+        ink::env::test::set_caller::<ink::env::DefaultEnvironment>(accounts.charlie);
+        assert_eq!(contract.toggle_sale_active(), Ok(()));
+        assert_eq!(contract.sale_active(), false);
+
+        // Simulate the owner calling the function
+        ink::env::test::set_caller::<ink::env::DefaultEnvironment>(accounts.charlie);
+
+        // Toggle sale active, which should set the sale time to 0
+        assert_eq!(contract.toggle_sale_active(), Ok(()));
+        assert_eq!(contract.sale_time(), 0);
+        assert_eq!(contract.sale_active(), true);
+
+        // Toggle sale active again, which should set the sale time to u64::MAX
+        assert_eq!(contract.toggle_sale_active(), Ok(()));
+        assert_eq!(contract.sale_time(), u64::MAX);
+        assert_eq!(contract.sale_active(), false);
+    }
+
+    #[ink::test]
+    fn withdraw_works() {
+        let accounts = default_accounts();
+        let mut contract = Contract::new(
+            "Test".to_string(),
+            "TST".to_string(),
+            "https://example.com/token/".to_string(),
+            10, // Assuming the price per token is 10
+            1000,
+            10,
+            100000,
+            Some(accounts.django),
+            Some(accounts.charlie),
+            0, // set sale time to 0 for testing
+        );
+
+        // Simulate buying a token
+        ink::env::test::set_account_balance::<ink::env::DefaultEnvironment>(accounts.alice, 0);
+        ink::env::test::set_account_balance::<ink::env::DefaultEnvironment>(accounts.charlie, 0);
+        ink::env::test::set_account_balance::<ink::env::DefaultEnvironment>(accounts.django, 0);
+        ink::env::test::set_account_balance::<ink::env::DefaultEnvironment>(
+            accounts.bob,
+            100_000_000,
+        );
+
+        ink::env::test::set_caller::<ink::env::DefaultEnvironment>(accounts.bob); // Setting the caller for the next contract call
+                                                                                  // Simulating value transferred for the next call - 10 tokens * 10 units each
+        assert_eq!(ink::env::pay_with_call!(contract.buy(10), 10_100), Ok(()));
+        assert_eq!(contract.get_account_balance(), 0);
+
+        // Check that owner's balance has increased by 10000 units
+        let dev_balance =
+            ink::env::test::get_account_balance::<ink::env::DefaultEnvironment>(accounts.charlie)
+                .unwrap_or_default();
+        assert_eq!(dev_balance, 100);
+
+        // Funds that reach the contract outside of `buy` sit on top of the existential deposit
+        let minimum_balance = ink::env::minimum_balance::<ink::env::DefaultEnvironment>();
+        ink::env::test::set_account_balance::<ink::env::DefaultEnvironment>(
+            accounts.alice,
+            minimum_balance + 5000,
+        );
+        ink::env::test::set_account_balance::<ink::env::DefaultEnvironment>(accounts.frank, 0);
+        assert_eq!(contract.withdrawable(), 5000);
+
+        // Simulate the owner calling the withdraw function
+        ink::env::test::set_caller::<ink::env::DefaultEnvironment>(accounts.alice);
+        assert_eq!(
+            contract.withdraw_to(accounts.frank, 5001),
+            Err(PSP34Error::Custom(String::from(
+                "DropspaceSale::withdraw_to: Amount exceeds withdrawable balance"
+            )))
+        );
+        assert_eq!(contract.withdraw_to(accounts.frank, 2000), Ok(()));
+        assert_eq!(
+            ink::env::test::get_account_balance::<ink::env::DefaultEnvironment>(accounts.frank),
+            Ok(2000)
+        );
+
+        // The rest goes to the withdraw wallet, not the caller
+        assert_eq!(contract.withdraw(), Ok(()));
+        assert_eq!(
+            ink::env::test::get_account_balance::<ink::env::DefaultEnvironment>(accounts.django),
+            Ok(10000 + 3000)
+        );
+        assert_eq!(contract.get_account_balance(), minimum_balance);
+        assert_eq!(
+            contract.withdraw(),
+            Err(PSP34Error::Custom(String::from("No funds to withdraw")))
+        );
+
+        assert_eq!(contract.set_withdraw_wallet(None), Ok(()));
+        assert_eq!(
+            contract.withdraw(),
+            Err(PSP34Error::Custom(String::from("Owner wallet not set")))
+        );
+    }
+
+    #[ink::test]
+    fn pause_works() {
+        let accounts = default_accounts();
+
+        // Set owner
+        ink::env::test::set_caller::<ink::env::DefaultEnvironment>(accounts.charlie);
+
+        let params = ContractParam {
+            withdraw_wallet: Some(accounts.django),
+            dev_wallet: Some(accounts.eve),
+            ..Default::default()
+        };
+        let mut contract = get_contract(&params);
+        assert_eq!(contract.reserve(2), Ok(()));
+        ink::env::test::set_account_balance::<ink::env::DefaultEnvironment>(
+            accounts.bob,
+            100_000_000,
+        );
+
+        // Only the owner can pause
+        ink::env::test::set_caller::<ink::env::DefaultEnvironment>(accounts.bob);
+        assert_eq!(
+            contract.pause(),
+            Err(PSP34Error::Custom(String::from("O::CallerIsNotOwner")))
+        );
+
+        ink::env::test::set_caller::<ink::env::DefaultEnvironment>(accounts.charlie);
+        assert_eq!(contract.pause(), Ok(()));
+        assert_eq!(Pausable::paused(&contract), true);
+
+        // Pausing doesn't touch the sale schedule
+        assert_eq!(contract.sale_time(), params.sale_time);
+        assert_eq!(
+            contract.reserve(1),
+            Err(PSP34Error::Custom(String::from("P::Paused")))
+        );
+
+        ink::env::test::set_caller::<ink::env::DefaultEnvironment>(accounts.bob);
+        assert_eq!(
+            ink::env::pay_with_call!(contract.buy(1), params.mint_price + params.mint_fee),
+            Err(PSP34Error::Custom(String::from("P::Paused")))
+        );
+
+        // Transfers keep working unless the owner opts in to blocking them
+        ink::env::test::set_caller::<ink::env::DefaultEnvironment>(accounts.charlie);
+        assert_eq!(
+            psp34::PSP34::transfer(&mut contract, accounts.frank, Id::U128(0), Vec::new()),
+            Ok(())
+        );
+        assert_eq!(contract.set_pause_transfers(true), Ok(()));
+        assert_eq!(contract.pause_transfers(), true);
+        assert_eq!(
+            psp34::PSP34::transfer(&mut contract, accounts.frank, Id::U128(1), Vec::new()),
+            Err(PSP34Error::Custom(String::from(
+                "DropspaceSale::transfer: Transfers are paused"
+            )))
+        );
+
+        assert_eq!(contract.unpause(), Ok(()));
+        assert_eq!(Pausable::paused(&contract), false);
+        assert_eq!(
+            psp34::PSP34::transfer(&mut contract, accounts.frank, Id::U128(1), Vec::new()),
+            Ok(())
+        );
+
+        ink::env::test::set_caller::<ink::env::DefaultEnvironment>(accounts.bob);
+        assert_eq!(
+            ink::env::pay_with_call!(contract.buy(1), params.mint_price + params.mint_fee),
+            Ok(())
+        );
+    }
+
+    #[ink::test]
+    fn airdrop_works() {
+        let accounts = default_accounts();
+
+        // Set owner
+        ink::env::test::set_caller::<ink::env::DefaultEnvironment>(accounts.charlie);
+
+        let params = ContractParam {
+            supply_limit: 10,
+            withdraw_wallet: Some(accounts.django),
+            dev_wallet: Some(accounts.eve),
+            ..Default::default()
+        };
+        let mut contract = get_contract(&params);
+
+        assert_eq!(
+            contract.airdrop(vec![(accounts.bob, 2), (accounts.django, 3)]),
+            Ok(())
+        );
+        assert_eq!(psp34::PSP34::total_supply(&contract), 5);
+        assert_eq!(psp34::PSP34::balance_of(&contract, accounts.bob), 2);
+        assert_eq!(psp34::PSP34::balance_of(&contract, accounts.django), 3);
+        assert_eq!(
+            psp34::PSP34::owner_of(&contract, Id::U128(4)),
+            Some(accounts.django)
+        );
+        assert_eq!(ink::env::test::recorded_events().count(), 2);
+
+        // Supply is checked for the whole batch before anything is minted
+        assert_eq!(
+            contract.airdrop(vec![(accounts.bob, 3), (accounts.eve, 3)]),
+            Err(PSP34Error::Custom(String::from(
+                "DropspaceSale::airdrop: Supply limit reached"
+            )))
+        );
+        assert_eq!(psp34::PSP34::total_supply(&contract), 5);
+
+        assert_eq!(
+            contract.airdrop(vec![(accounts.bob, MAX_AIRDROP_PER_CALL + 1)]),
+            Err(PSP34Error::Custom(String::from(
+                "DropspaceSale::airdrop: Can't exceed amount of mints per call"
+            )))
+        );
+
+        ink::env::test::set_caller::<ink::env::DefaultEnvironment>(accounts.bob);
+        assert_eq!(
+            contract.airdrop(vec![(accounts.bob, 1)]),
+            Err(PSP34Error::Custom(String::from("O::CallerIsNotOwner")))
+        );
+    }
+
+    #[ink::test]
+    fn buy_for_works() {
+        let accounts = default_accounts();
+
+        // Set owner
+        ink::env::test::set_caller::<ink::env::DefaultEnvironment>(accounts.charlie);
+
+        let params = ContractParam {
+            withdraw_wallet: Some(accounts.django),
+            dev_wallet: Some(accounts.eve),
+            ..Default::default()
+        };
+        let mut contract = get_contract(&params);
+        assert_eq!(contract.set_mint_per_wallet(3), Ok(()));
+
+        ink::env::test::set_account_balance::<ink::env::DefaultEnvironment>(
+            accounts.bob,
+            100_000_000,
+        );
+        let unit_price = params.mint_price + params.mint_fee;
+
+        // Bob pays, Frank receives
+        ink::env::test::set_caller::<ink::env::DefaultEnvironment>(accounts.bob);
+        assert_eq!(
+            ink::env::pay_with_call!(contract.buy_for(accounts.frank, 2), 2 * unit_price),
+            Ok(())
+        );
+        assert_eq!(psp34::PSP34::balance_of(&contract, accounts.frank), 2);
+        assert_eq!(psp34::PSP34::balance_of(&contract, accounts.bob), 0);
+        assert_eq!(
+            ink::env::test::get_account_balance::<ink::env::DefaultEnvironment>(accounts.bob),
+            Ok(100_000_000 - 2 * unit_price)
+        );
+        assert_eq!(ink::env::test::recorded_events().count(), 1);
+
+        // The wallet limit is tracked against the recipient, not the payer
+        assert_eq!(contract.minted(accounts.frank), 2);
+        assert_eq!(contract.minted(accounts.bob), 0);
+        assert_eq!(
+            ink::env::pay_with_call!(contract.buy_for(accounts.frank, 2), 2 * unit_price),
+            Err(PSP34Error::Custom(String::from(
+                "DropspaceSale::buy: Can't exceed amount of mints per wallet"
+            )))
+        );
+        assert_eq!(
+            ink::env::pay_with_call!(contract.buy(3), 3 * unit_price),
+            Ok(())
+        );
+        assert_eq!(contract.minted(accounts.bob), 3);
+    }
+
+    #[ink::test]
+    fn payment_assets_work() {
+        let accounts = default_accounts();
+
+        // Set owner
+        ink::env::test::set_caller::<ink::env::DefaultEnvironment>(accounts.charlie);
+
+        let params = ContractParam {
+            withdraw_wallet: Some(accounts.django),
+            dev_wallet: Some(accounts.eve),
+            ..Default::default()
+        };
+        let mut contract = get_contract(&params);
+        let token = PaymentAsset::Psp22(accounts.frank);
+
+        // Constructor prices become the native entry
+        assert_eq!(contract.payment_assets(), vec![PaymentAsset::Native]);
+        assert_eq!(
+            contract.payment_price(PaymentAsset::Native),
+            Some((params.mint_price, params.mint_fee))
+        );
+        assert_eq!(
+            contract.quote(PaymentAsset::Native, 3, accounts.bob).total,
+            3 * (params.mint_price + params.mint_fee)
+        );
+
+        ink::env::test::set_caller::<ink::env::DefaultEnvironment>(accounts.bob);
+        assert_eq!(
+            contract.buy_with_psp22(accounts.frank, 1),
+            Err(PSP34Error::Custom(String::from(
+                "DropspaceSale::buy: Payment asset not accepted"
+            )))
+        );
+        assert_eq!(
+            contract.add_payment_asset(token, 500, 5),
+            Err(PSP34Error::Custom(String::from("O::CallerIsNotOwner")))
+        );
+
+        ink::env::test::set_caller::<ink::env::DefaultEnvironment>(accounts.charlie);
+        assert_eq!(contract.add_payment_asset(token, 500, 5), Ok(()));
+        assert_eq!(contract.add_payment_asset(token, 600, 6), Ok(()));
+        assert_eq!(contract.payment_assets(), vec![PaymentAsset::Native, token]);
+        assert_eq!(contract.quote(token, 2, accounts.bob).total, 2 * 606);
+
+        // Native entry stays in sync with the legacy setters and getters
+        assert_eq!(contract.set_mint_price(2000), Ok(()));
+        assert_eq!(contract.mint_price(), 2000);
+        assert_eq!(contract.mint_fee(), params.mint_fee);
+        assert_eq!(
+            contract.add_payment_asset(PaymentAsset::Native, 3000, 30),
+            Ok(())
+        );
+        assert_eq!(contract.mint_price(), 3000);
+        assert_eq!(contract.mint_fee(), 30);
+
+        // Sale checks run before any token is pulled
+        ink::env::test::set_caller::<ink::env::DefaultEnvironment>(accounts.bob);
+        assert_eq!(
+            contract.buy_with_psp22(accounts.frank, params.mint_per_tx + 1),
+            Err(PSP34Error::Custom(String::from(
+                "DropspaceSale::buy: Can't exceed amount of mints per tx"
+            )))
+        );
+
+        ink::env::test::set_caller::<ink::env::DefaultEnvironment>(accounts.charlie);
+        assert_eq!(
+            contract.remove_payment_asset(PaymentAsset::Native),
+            Err(PSP34Error::Custom(String::from(
+                "DropspaceSale::remove_payment_asset: Native currency can't be removed"
+            )))
+        );
+        assert_eq!(contract.remove_payment_asset(token), Ok(()));
+        assert_eq!(contract.payment_assets(), vec![PaymentAsset::Native]);
+        assert_eq!(
+            contract.quote(token, 1, accounts.bob).rejection,
+            Some(PSP34Error::Custom(String::from(
+                "DropspaceSale::buy: Payment asset not accepted"
+            )))
+        );
+    }
+
+    #[ink::test]
+    fn sale_info_works() {
+        let accounts = default_accounts();
+
+        // Set owner
+        ink::env::test::set_caller::<ink::env::DefaultEnvironment>(accounts.charlie);
+
+        let params = ContractParam {
+            withdraw_wallet: Some(accounts.django),
+            dev_wallet: Some(accounts.eve),
+            ..Default::default()
+        };
+        let mut contract = get_contract(&params);
+        assert_eq!(contract.reserve(3), Ok(()));
+        ink::env::test::set_block_timestamp::<ink::env::DefaultEnvironment>(12345678);
+
+        let info = contract.sale_info();
+        assert_eq!(info.base_uri, params.base_uri);
+        assert_eq!(info.supply_limit, params.supply_limit);
+        assert_eq!(info.mint_per_tx, params.mint_per_tx);
+        assert_eq!(info.mint_price, params.mint_price);
+        assert_eq!(info.mint_fee, params.mint_fee);
+        assert_eq!(info.withdraw_wallet, params.withdraw_wallet);
+        assert_eq!(info.dev_wallet, params.dev_wallet);
+        assert_eq!(info.sale_time, params.sale_time);
+        assert_eq!(info.sale_active, true);
+        assert_eq!(info.paused, false);
+        assert_eq!(info.total_supply, 3);
+        assert_eq!(info.block_timestamp, 12345678);
+
+        assert_eq!(
+            contract.update_sale_config(SaleConfigUpdate {
+                mint_price: Some(2000),
+                sale_time: Some(u64::MAX),
+                withdraw_wallet: Some(None),
+                ..Default::default()
+            }),
+            Ok(())
+        );
+        let info = contract.sale_info();
+        assert_eq!(info.mint_price, 2000);
+        assert_eq!(info.mint_fee, params.mint_fee);
+        assert_eq!(info.sale_active, false);
+        assert_eq!(info.withdraw_wallet, None);
+        assert_eq!(info.mint_per_tx, params.mint_per_tx);
+
+        // A failing field leaves the rest of the update unapplied
+        assert_eq!(
+            contract.update_sale_config(SaleConfigUpdate {
+                mint_per_tx: Some(1),
+                supply_limit: Some(2),
+                ..Default::default()
+            }),
+            Err(PSP34Error::Custom(String::from(
+                "DropspaceSale::update_sale_config: Supply limit is lesser than current supply"
+            )))
+        );
+        assert_eq!(contract.mint_per_tx(), params.mint_per_tx);
+        assert_eq!(contract.supply_limit(), params.supply_limit);
+
+        ink::env::test::set_caller::<ink::env::DefaultEnvironment>(accounts.bob);
+        assert_eq!(
+            contract.update_sale_config(SaleConfigUpdate::default()),
+            Err(PSP34Error::Custom(String::from("O::CallerIsNotOwner")))
+        );
+    }
+
+    #[ink::test]
+    fn quote_works() {
+        let accounts = default_accounts();
+
+        // Set owner
+        ink::env::test::set_caller::<ink::env::DefaultEnvironment>(accounts.charlie);
+
+        let params = ContractParam {
+            withdraw_wallet: Some(accounts.django),
+            dev_wallet: Some(accounts.eve),
+            ..Default::default()
+        };
+        let mut contract = get_contract(&params);
+        ink::env::test::set_account_balance::<ink::env::DefaultEnvironment>(
+            accounts.bob,
+            100_000_000,
+        );
+
+        let quote = contract.quote(PaymentAsset::Native, 4, accounts.bob);
+        assert_eq!(quote.price, 4 * params.mint_price);
+        assert_eq!(quote.fee, 4 * params.mint_fee);
+        assert_eq!(quote.total, 4 * (params.mint_price + params.mint_fee));
+        assert_eq!(quote.rejection, None);
+
+        // Rejections match what `buy` returns
+        let quote = contract.quote(PaymentAsset::Native, params.mint_per_tx + 1, accounts.bob);
+        assert_eq!(
+            quote.rejection,
+            Some(PSP34Error::Custom(String::from(
+                "DropspaceSale::buy: Can't exceed amount of mints per tx"
+            )))
+        );
+
+        assert_eq!(contract.set_mint_per_wallet(2), Ok(()));
+        ink::env::test::set_caller::<ink::env::DefaultEnvironment>(accounts.bob);
+        assert_eq!(
+            ink::env::pay_with_call!(contract.buy(2), 2 * (params.mint_price + params.mint_fee)),
+            Ok(())
+        );
+        let quote = contract.quote(PaymentAsset::Native, 1, accounts.bob);
+        assert_eq!(quote.total, 0);
+        assert_eq!(
+            quote.rejection,
+            Some(PSP34Error::Custom(String::from(
+                "DropspaceSale::buy: Can't exceed amount of mints per wallet"
+            )))
+        );
+        assert_eq!(
+            contract
+                .quote(PaymentAsset::Native, 1, accounts.frank)
+                .rejection,
+            None
+        );
+
+        ink::env::test::set_caller::<ink::env::DefaultEnvironment>(accounts.charlie);
+        assert_eq!(contract.pause(), Ok(()));
+        assert_eq!(
+            contract
+                .quote(PaymentAsset::Native, 1, accounts.frank)
+                .rejection,
+            Some(PSP34Error::Custom(String::from("P::Paused")))
+        );
+    }
+
+    #[ink::test]
+    fn pricing_works() {
+        let accounts = default_accounts();
+
+        // Set owner
+        ink::env::test::set_caller::<ink::env::DefaultEnvironment>(accounts.charlie);
+
+        let params = ContractParam {
+            withdraw_wallet: Some(accounts.django),
+            dev_wallet: Some(accounts.eve),
+            ..Default::default()
+        };
+        let mut contract = get_contract(&params);
+        assert_eq!(
+            contract.set_pricing(PricingModel::Step {
+                step_size: 0,
+                increment: 100
+            }),
+            Err(PSP34Error::Custom(String::from(
+                "DropspaceSale::set_pricing: Step size can't be zero"
+            )))
+        );
+        assert_eq!(
+            contract.set_pricing(PricingModel::Step {
+                step_size: 3,
+                increment: 100
+            }),
+            Ok(())
+        );
+        assert_eq!(contract.reserve(2), Ok(()));
+
+        // The batch crosses from the first step into the second
+        let quote = contract.quote(PaymentAsset::Native, 4, accounts.bob);
+        assert_eq!(quote.breakdown, vec![(1, 1000), (3, 1100)]);
+        assert_eq!(quote.price, 4300);
+        assert_eq!(quote.fee, 4 * params.mint_fee);
+
+        ink::env::test::set_account_balance::<ink::env::DefaultEnvironment>(
+            accounts.bob,
+            100_000_000,
+        );
+        ink::env::test::set_caller::<ink::env::DefaultEnvironment>(accounts.bob);
+        assert_eq!(
+            ink::env::pay_with_call!(contract.buy(4), 4 * (params.mint_price + params.mint_fee)),
+            Err(PSP34Error::Custom(String::from(
+                "DropspaceSale::buy: Wrong amount paid."
+            )))
+        );
+        assert_eq!(ink::env::pay_with_call!(contract.buy(4), 4340), Ok(()));
+        assert_eq!(contract.raised(PaymentAsset::Native), (4300, 40));
+
+        ink::env::test::set_caller::<ink::env::DefaultEnvironment>(accounts.charlie);
+        assert_eq!(
+            contract.set_pricing(PricingModel::Linear { increment: 10 }),
+            Ok(())
+        );
+        let quote = contract.quote(PaymentAsset::Native, 2, accounts.bob);
+        assert_eq!(quote.breakdown, vec![(1, 1060), (1, 1070)]);
+        assert_eq!(quote.total, 2130 + 2 * params.mint_fee);
+
+        // Over the limits an amount is rejected before it's priced
+        let quote = contract.quote(PaymentAsset::Native, u128::MAX, accounts.bob);
+        assert_eq!(
+            quote.rejection,
+            Some(PSP34Error::Custom(String::from(
+                "DropspaceSale::buy: Supply limit reached"
+            )))
+        );
+
+        // Totals across several steps match the run-by-run breakdown
+        assert_eq!(
+            contract.set_pricing(PricingModel::Step {
+                step_size: 4,
+                increment: 100
+            }),
+            Ok(())
+        );
+        let quote = contract.quote(PaymentAsset::Native, 9, accounts.bob);
+        assert_eq!(quote.breakdown, vec![(2, 1100), (4, 1200), (3, 1300)]);
+        assert_eq!(quote.price, 10_900);
+    }
+
+    #[ink::test]
+    fn pricing_after_refund_works() {
+        let accounts = default_accounts();
+
+        // Set owner
+        ink::env::test::set_caller::<ink::env::DefaultEnvironment>(accounts.charlie);
+
+        let params = ContractParam {
+            withdraw_wallet: Some(accounts.django),
+            dev_wallet: Some(accounts.eve),
+            ..Default::default()
+        };
+        let mut contract = get_contract(&params);
+        assert_eq!(contract.set_refund_window(1000, false), Ok(()));
+        assert_eq!(
+            contract.set_pricing(PricingModel::Linear { increment: 10 }),
+            Ok(())
+        );
+        ink::env::test::set_account_balance::<ink::env::DefaultEnvironment>(
+            accounts.bob,
+            100_000_000,
+        );
+
+        ink::env::test::set_caller::<ink::env::DefaultEnvironment>(accounts.bob);
+        assert_eq!(ink::env::pay_with_call!(contract.buy(2), 2030), Ok(()));
+        assert_eq!(contract.refund(vec![1]), Ok(()));
+        assert_eq!(psp34::PSP34::total_supply(&contract), 1);
+
+        // Burning a refunded token doesn't move the curve back
+        let quote = contract.quote(PaymentAsset::Native, 1, accounts.bob);
+        assert_eq!(quote.breakdown, vec![(1, 1020)]);
+    }
+
+    #[ink::test]
+    fn bulk_discount_works() {
+        let accounts = default_accounts();
+
+        // Set owner
+        ink::env::test::set_caller::<ink::env::DefaultEnvironment>(accounts.charlie);
+
+        let params = ContractParam {
+            withdraw_wallet: Some(accounts.django),
+            dev_wallet: Some(accounts.eve),
+            ..Default::default()
+        };
+        let mut contract = get_contract(&params);
+        assert_eq!(
+            contract.set_bulk_discounts(vec![(5, BPS_DENOMINATOR + 1)], false),
+            Err(PSP34Error::Custom(String::from(
+                "DropspaceSale::set_bulk_discounts: Discount can't exceed 100%"
+            )))
+        );
+        assert_eq!(
+            contract.set_bulk_discounts(vec![(5, 1000), (8, 2000)], false),
+            Ok(())
+        );
+
+        let quote = contract.quote(PaymentAsset::Native, 4, accounts.bob);
+        assert_eq!(quote.discount, 0);
+        assert_eq!(quote.total, 4 * (params.mint_price + params.mint_fee));
+
+        // Only the price part is discounted
+        let quote = contract.quote(PaymentAsset::Native, 5, accounts.bob);
+        assert_eq!(quote.price, 4500);
+        assert_eq!(quote.fee, 5 * params.mint_fee);
+        assert_eq!(quote.discount, 500);
+
+        // The best tier a purchase qualifies for applies
+        let quote = contract.quote(PaymentAsset::Native, 10, accounts.bob);
+        assert_eq!(quote.price, 8000);
+
+        ink::env::test::set_account_balance::<ink::env::DefaultEnvironment>(
+            accounts.bob,
+            100_000_000,
+        );
+        ink::env::test::set_caller::<ink::env::DefaultEnvironment>(accounts.bob);
+        assert_eq!(ink::env::pay_with_call!(contract.buy(5), 4550), Ok(()));
+        assert_eq!(contract.raised(PaymentAsset::Native), (4500, 50));
+
+        ink::env::test::set_caller::<ink::env::DefaultEnvironment>(accounts.charlie);
+        assert_eq!(contract.set_bulk_discounts(vec![(5, 1000)], true), Ok(()));
+        let quote = contract.quote(PaymentAsset::Native, 5, accounts.bob);
+        assert_eq!(quote.fee, 45);
+        assert_eq!(quote.discount, 505);
+    }
+
+    #[ink::test]
+    fn promo_code_works() {
+        let accounts = default_accounts();
+
+        // Set owner
+        ink::env::test::set_caller::<ink::env::DefaultEnvironment>(accounts.charlie);
+
+        let params = ContractParam {
+            withdraw_wallet: Some(accounts.django),
+            dev_wallet: Some(accounts.eve),
+            ..Default::default()
+        };
+        let mut contract = get_contract(&params);
+
+        let code_hash = |code: &str| {
+            let mut hash = <Blake2x256 as HashOutput>::Type::default();
+            ink::env::hash_bytes::<Blake2x256>(code.as_bytes(), &mut hash);
+            hash
+        };
+        assert_eq!(
+            contract.add_promo_code(code_hash("ALICE10"), BPS_DENOMINATOR + 1, false, 1, 1000),
+            Err(PSP34Error::Custom(String::from(
+                "DropspaceSale::add_promo_code: Discount can't exceed 100%"
+            )))
+        );
+        assert_eq!(
+            contract.add_promo_code(code_hash("ALICE10"), 1000, false, 1, 1000),
+            Ok(())
+        );
+        assert_eq!(
+            contract.add_promo_code(code_hash("EARLY"), 5000, false, 10, 500),
+            Ok(())
+        );
+        assert_eq!(
+            contract.add_promo_code(code_hash("FEELESS"), 5000, true, 10, 1000),
+            Ok(())
+        );
+
+        ink::env::test::set_account_balance::<ink::env::DefaultEnvironment>(
+            accounts.bob,
+            100_000_000,
+        );
+        ink::env::test::set_caller::<ink::env::DefaultEnvironment>(accounts.bob);
+        assert_eq!(
+            ink::env::pay_with_call!(contract.buy_with_code(1, "BOB10".to_string()), 910),
+            Err(PSP34Error::Custom(String::from(
+                "DropspaceSale::buy_with_code: Invalid promo code"
+            )))
+        );
+        assert_eq!(
+            ink::env::pay_with_call!(contract.buy_with_code(1, "ALICE10".to_string()), 909),
+            Err(PSP34Error::Custom(String::from(
+                "DropspaceSale::buy: Wrong amount paid."
+            )))
+        );
+        assert_eq!(
+            ink::env::pay_with_call!(contract.buy_with_code(1, "ALICE10".to_string()), 910),
+            Ok(())
+        );
+        assert_eq!(
+            contract
+                .promo_code(code_hash("ALICE10"))
+                .map(|promo| promo.uses),
+            Some(1)
+        );
+        assert_eq!(contract.raised(PaymentAsset::Native), (900, 10));
+        assert_eq!(
+            ink::env::pay_with_call!(contract.buy_with_code(1, "ALICE10".to_string()), 910),
+            Err(PSP34Error::Custom(String::from(
+                "DropspaceSale::buy_with_code: Promo code used up"
+            )))
+        );
+
+        // A code can cut the fee even though bulk discounts leave it alone
+        assert!(!contract.discount_fee());
+        assert_eq!(
+            ink::env::pay_with_call!(contract.buy_with_code(1, "FEELESS".to_string()), 505),
+            Ok(())
+        );
+        assert_eq!(contract.raised(PaymentAsset::Native), (1400, 15));
+
+        ink::env::test::set_block_timestamp::<ink::env::DefaultEnvironment>(500);
+        assert_eq!(
+            ink::env::pay_with_call!(contract.buy_with_code(1, "EARLY".to_string()), 510),
+            Err(PSP34Error::Custom(String::from(
+                "DropspaceSale::buy_with_code: Promo code expired"
+            )))
+        );
+
+        ink::env::test::set_caller::<ink::env::DefaultEnvironment>(accounts.charlie);
+        assert_eq!(contract.remove_promo_code(code_hash("EARLY")), Ok(()));
+        assert_eq!(contract.promo_code(code_hash("EARLY")), None);
+    }
+
+    #[ink::test]
+    fn enumerable_works() {
+        let accounts = default_accounts();
+
+        // Set owner
+        ink::env::test::set_caller::<ink::env::DefaultEnvironment>(accounts.charlie);
+
+        let params = ContractParam {
+            withdraw_wallet: Some(accounts.django),
+            dev_wallet: Some(accounts.eve),
+            ..Default::default()
+        };
+        let mut contract = get_contract(&params);
+        assert_eq!(contract.reserve(2), Ok(()));
+
+        ink::env::test::set_account_balance::<ink::env::DefaultEnvironment>(
+            accounts.bob,
+            100_000_000,
+        );
+        ink::env::test::set_caller::<ink::env::DefaultEnvironment>(accounts.bob);
+        assert_eq!(
+            ink::env::pay_with_call!(contract.buy(3), 3 * (params.mint_price + params.mint_fee)),
+            Ok(())
+        );
+
+        assert_eq!(
+            contract.tokens_of_owner(accounts.charlie, 0, 10),
+            vec![Id::U128(0), Id::U128(1)]
+        );
+        assert_eq!(
+            contract.tokens_of_owner(accounts.bob, 0, 10),
+            vec![Id::U128(2), Id::U128(3), Id::U128(4)]
+        );
+        assert_eq!(
+            contract.tokens_of_owner(accounts.bob, 1, 1),
+            vec![Id::U128(3)]
+        );
+        assert_eq!(contract.tokens_of_owner(accounts.bob, 5, 10), vec![]);
+        assert_eq!(
+            PSP34Enumerable::token_by_index(&contract, 4),
+            Ok(Id::U128(4))
+        );
+        assert_eq!(
+            PSP34Enumerable::token_by_index(&contract, 5),
+            Err(PSP34Error::TokenNotExists)
+        );
+
+        // Indexes follow transfers
+        assert_eq!(
+            psp34::PSP34::transfer(&mut contract, accounts.frank, Id::U128(2), Vec::new()),
+            Ok(())
+        );
+        let bob_tokens = contract.tokens_of_owner(accounts.bob, 0, 10);
+        assert_eq!(bob_tokens.len(), 2);
+        assert!(bob_tokens.contains(&Id::U128(3)));
+        assert!(bob_tokens.contains(&Id::U128(4)));
+        assert_eq!(
+            contract.tokens_of_owner(accounts.frank, 0, 10),
+            vec![Id::U128(2)]
+        );
+        assert_eq!(
+            PSP34Enumerable::owners_token_by_index(&contract, accounts.frank, 0),
+            Ok(Id::U128(2))
+        );
+        assert_eq!(psp34::PSP34::total_supply(&contract), 5);
+    }
+
+    #[ink::test]
+    fn sale_stats_work() {
+        let accounts = default_accounts();
+
+        // Set owner
+        ink::env::test::set_caller::<ink::env::DefaultEnvironment>(accounts.charlie);
+
+        let params = ContractParam {
+            withdraw_wallet: Some(accounts.django),
+            dev_wallet: Some(accounts.eve),
+            ..Default::default()
+        };
+        let mut contract = get_contract(&params);
+        assert_eq!(contract.sale_stats(), SaleStats::default());
+
+        assert_eq!(contract.reserve(2), Ok(()));
+        assert_eq!(contract.airdrop(vec![(accounts.frank, 1)]), Ok(()));
+
+        ink::env::test::set_account_balance::<ink::env::DefaultEnvironment>(
+            accounts.bob,
+            100_000_000,
+        );
+        ink::env::test::set_account_balance::<ink::env::DefaultEnvironment>(accounts.django, 0);
+        ink::env::test::set_account_balance::<ink::env::DefaultEnvironment>(accounts.eve, 0);
+        let unit_price = params.mint_price + params.mint_fee;
+
+        ink::env::test::set_caller::<ink::env::DefaultEnvironment>(accounts.bob);
+        assert_eq!(
+            ink::env::pay_with_call!(contract.buy(2), 2 * unit_price),
+            Ok(())
+        );
+        assert_eq!(
+            ink::env::pay_with_call!(contract.buy(1), unit_price),
+            Ok(())
+        );
+        assert_eq!(
+            ink::env::pay_with_call!(contract.buy_for(accounts.frank, 1), unit_price),
+            Ok(())
+        );
+
+        assert_eq!(
+            contract.sale_stats(),
+            SaleStats {
+                tokens_sold: 4,
+                tokens_reserved: 3,
+                tokens_redeemed: 0,
+                unique_buyers: 2,
+            }
+        );
+        assert_eq!(
+            contract.raised(PaymentAsset::Native),
+            (4 * params.mint_price, 4 * params.mint_fee)
+        );
+        assert_eq!(contract.raised(PaymentAsset::Psp22(accounts.frank)), (0, 0));
+        assert_eq!(contract.refunded(PaymentAsset::Native), 0);
+        assert_eq!(contract.withdrawn(accounts.django), 4 * params.mint_price);
+        assert_eq!(contract.withdrawn(accounts.eve), 4 * params.mint_fee);
+        assert_eq!(contract.withdrawn(accounts.bob), 0);
+    }
+
+    #[ink::test]
+    fn rescue_psp34_works() {
+        let accounts = default_accounts();
+
+        // Set owner
+        ink::env::test::set_caller::<ink::env::DefaultEnvironment>(accounts.charlie);
+
+        let params = ContractParam {
+            withdraw_wallet: Some(accounts.django),
+            dev_wallet: Some(accounts.eve),
+            ..Default::default()
+        };
+        let mut contract = get_contract(&params);
+        let contract_id = ink::env::account_id::<ink::env::DefaultEnvironment>();
+
+        // Someone sends one of the collection's own tokens to the sale contract
+        assert_eq!(
+            contract.airdrop(vec![(contract_id, 1), (accounts.bob, 1)]),
+            Ok(())
+        );
+
+        ink::env::test::set_caller::<ink::env::DefaultEnvironment>(accounts.bob);
+        assert_eq!(
+            contract.rescue_psp34(contract_id, Id::U128(0), accounts.frank),
+            Err(PSP34Error::Custom(String::from("O::CallerIsNotOwner")))
+        );
+
+        ink::env::test::set_caller::<ink::env::DefaultEnvironment>(accounts.charlie);
+        assert_eq!(
+            contract.rescue_psp34(contract_id, Id::U128(1), accounts.frank),
+            Err(PSP34Error::Custom(String::from(
+                "DropspaceSale::rescue_psp34: Token not held by contract"
+            )))
+        );
+
+        // A rescue is a transfer too, so it waits while transfers are paused
+        assert_eq!(contract.set_pause_transfers(true), Ok(()));
+        assert_eq!(contract.pause(), Ok(()));
+        assert_eq!(
+            contract.rescue_psp34(contract_id, Id::U128(0), accounts.frank),
+            Err(PSP34Error::Custom(String::from(
+                "DropspaceSale::rescue_psp34: Transfers are paused"
+            )))
+        );
+
+        assert_eq!(contract.unpause(), Ok(()));
+        assert_eq!(
+            contract.rescue_psp34(contract_id, Id::U128(0), accounts.frank),
+            Ok(())
+        );
+        assert_eq!(
+            psp34::PSP34::owner_of(&contract, Id::U128(0)),
+            Some(accounts.frank)
+        );
+        assert_eq!(psp34::PSP34::balance_of(&contract, contract_id), 0);
+        assert_eq!(psp34::PSP34::total_supply(&contract), 2);
+    }
+
+    #[ink::test]
+    fn non_reentrant_works() {
+        let accounts = default_accounts();
+
+        // Set owner
+        ink::env::test::set_caller::<ink::env::DefaultEnvironment>(accounts.charlie);
+
+        let params = ContractParam {
+            withdraw_wallet: Some(accounts.django),
+            dev_wallet: Some(accounts.eve),
+            ..Default::default()
+        };
+        let mut contract = get_contract(&params);
+        ink::env::test::set_account_balance::<ink::env::DefaultEnvironment>(
+            accounts.bob,
+            100_000_000,
+        );
+
+        // Calls made while another guarded call is still running are turned away
+        Storage::<reentrancy_guard::Data>::data(&mut contract)
+            .status
+            .set(&reentrancy_guard::ENTERED);
+        assert_eq!(
+            contract.withdraw(),
+            Err(ReentrancyGuardError::ReentrantCall.into())
+        );
+        ink::env::test::set_caller::<ink::env::DefaultEnvironment>(accounts.bob);
+        assert_eq!(
+            ink::env::pay_with_call!(contract.buy(1), params.mint_price + params.mint_fee),
+            Err(ReentrancyGuardError::ReentrantCall.into())
+        );
+        assert_eq!(psp34::PSP34::total_supply(&contract), 0);
+
+        Storage::<reentrancy_guard::Data>::data(&mut contract)
+            .status
+            .set(&reentrancy_guard::NOT_ENTERED);
+        assert_eq!(
+            ink::env::pay_with_call!(contract.buy(1), params.mint_price + params.mint_fee),
+            Ok(())
+        );
+        assert_eq!(psp34::PSP34::total_supply(&contract), 1);
+    }
+
+    #[ink::test]
+    fn eoa_only_works() {
+        let accounts = default_accounts();
+
+        // Set owner
+        ink::env::test::set_caller::<ink::env::DefaultEnvironment>(accounts.charlie);
+
+        let params = ContractParam {
+            withdraw_wallet: Some(accounts.django),
+            dev_wallet: Some(accounts.eve),
+            ..Default::default()
+        };
+        let mut contract = get_contract(&params);
+        ink::env::test::set_account_balance::<ink::env::DefaultEnvironment>(
+            accounts.bob,
+            100_000_000,
+        );
+        assert_eq!(contract.eoa_only(), false);
+
+        ink::env::test::set_caller::<ink::env::DefaultEnvironment>(accounts.bob);
+        assert_eq!(
+            contract.set_eoa_only(true),
+            Err(PSP34Error::Custom(String::from("O::CallerIsNotOwner")))
+        );
+        assert_eq!(
+            contract.add_trusted_caller(accounts.bob),
+            Err(PSP34Error::Custom(String::from("O::CallerIsNotOwner")))
+        );
+
+        ink::env::test::set_caller::<ink::env::DefaultEnvironment>(accounts.charlie);
+        assert_eq!(contract.set_eoa_only(true), Ok(()));
+        assert_eq!(contract.add_trusted_caller(accounts.bob), Ok(()));
+        assert_eq!(contract.eoa_only(), true);
+        assert_eq!(contract.sale_info().eoa_only, true);
+        assert_eq!(contract.is_trusted_caller(accounts.bob), true);
+        assert_eq!(contract.is_trusted_caller(accounts.frank), false);
+
+        // Trusted callers skip the origin check entirely
+        ink::env::test::set_caller::<ink::env::DefaultEnvironment>(accounts.bob);
+        assert_eq!(
+            ink::env::pay_with_call!(contract.buy(1), params.mint_price + params.mint_fee),
+            Ok(())
+        );
+
+        ink::env::test::set_caller::<ink::env::DefaultEnvironment>(accounts.charlie);
+        assert_eq!(contract.remove_trusted_caller(accounts.bob), Ok(()));
+        assert_eq!(contract.is_trusted_caller(accounts.bob), false);
+    }
+
+    #[ink::test]
+    fn rate_limits_work() {
+        let accounts = default_accounts();
+
+        // Set owner
+        ink::env::test::set_caller::<ink::env::DefaultEnvironment>(accounts.charlie);
+
+        let params = ContractParam {
+            withdraw_wallet: Some(accounts.django),
+            dev_wallet: Some(accounts.eve),
+            ..Default::default()
+        };
+        let mut contract = get_contract(&params);
+        assert_eq!(
+            contract.update_sale_config(SaleConfigUpdate {
+                mint_per_block: Some(3),
+                purchase_cooldown: Some(60_000),
+                ..Default::default()
+            }),
+            Ok(())
+        );
+        assert_eq!(contract.mint_per_block(), 3);
+        assert_eq!(contract.purchase_cooldown(), 60_000);
+
+        for buyer in [accounts.bob, accounts.django, accounts.frank] {
+            ink::env::test::set_account_balance::<ink::env::DefaultEnvironment>(buyer, 100_000_000);
+        }
+        let unit_price = params.mint_price + params.mint_fee;
+        ink::env::test::set_block_timestamp::<ink::env::DefaultEnvironment>(1_000_000);
+
+        ink::env::test::set_caller::<ink::env::DefaultEnvironment>(accounts.bob);
+        assert_eq!(
+            ink::env::pay_with_call!(contract.buy(2), 2 * unit_price),
+            Ok(())
+        );
+        assert_eq!(contract.last_purchase(accounts.bob), Some(1_000_000));
+
+        // Block cap is global across wallets
+        ink::env::test::set_caller::<ink::env::DefaultEnvironment>(accounts.frank);
+        assert_eq!(
+            ink::env::pay_with_call!(contract.buy(2), 2 * unit_price),
+            Err(PSP34Error::Custom(String::from(
+                "DropspaceSale::buy: Can't exceed amount of mints per block"
+            )))
+        );
+        assert_eq!(
+            ink::env::pay_with_call!(contract.buy(1), unit_price),
+            Ok(())
+        );
+
+        // The cooldown follows the paying wallet, so a gift can't stall the recipient's own buys
+        ink::env::test::advance_block::<ink::env::DefaultEnvironment>();
+        ink::env::test::set_block_timestamp::<ink::env::DefaultEnvironment>(1_030_000);
+        ink::env::test::set_caller::<ink::env::DefaultEnvironment>(accounts.django);
+        assert_eq!(
+            ink::env::pay_with_call!(contract.buy_for(accounts.bob, 2), 2 * unit_price),
+            Ok(())
+        );
+        assert_eq!(contract.last_purchase(accounts.bob), Some(1_000_000));
+        assert_eq!(contract.last_purchase(accounts.django), Some(1_030_000));
+        assert_eq!(
+            ink::env::pay_with_call!(contract.buy_for(accounts.frank, 1), unit_price),
+            Err(PSP34Error::Custom(String::from(
+                "DropspaceSale::buy: Wallet is cooling down"
+            )))
+        );
+
+        // Bob's wallet is still cooling down
+        ink::env::test::advance_block::<ink::env::DefaultEnvironment>();
+        ink::env::test::set_block_timestamp::<ink::env::DefaultEnvironment>(1_059_999);
+        ink::env::test::set_caller::<ink::env::DefaultEnvironment>(accounts.bob);
+        assert_eq!(
+            ink::env::pay_with_call!(contract.buy(1), unit_price),
+            Err(PSP34Error::Custom(String::from(
+                "DropspaceSale::buy: Wallet is cooling down"
+            )))
+        );
+        ink::env::test::set_block_timestamp::<ink::env::DefaultEnvironment>(1_060_000);
+        assert_eq!(
+            ink::env::pay_with_call!(contract.buy(1), unit_price),
+            Ok(())
+        );
+    }
+
+    #[ink::test]
+    fn referral_works() {
+        let accounts = default_accounts();
+
+        // Set owner
+        ink::env::test::set_caller::<ink::env::DefaultEnvironment>(accounts.charlie);
+
+        let params = ContractParam {
+            withdraw_wallet: Some(accounts.django),
+            dev_wallet: Some(accounts.eve),
+            ..Default::default()
+        };
+        let mut contract = get_contract(&params);
+        assert_eq!(
+            contract.set_referral_bps(BPS_DENOMINATOR + 1),
+            Err(PSP34Error::Custom(String::from(
+                "DropspaceSale::set_referral_bps: Commission can't exceed 100%"
+            )))
+        );
+        assert_eq!(contract.set_referral_bps(500), Ok(()));
+
+        let contract_id = ink::env::account_id::<ink::env::DefaultEnvironment>();
+        let minimum_balance = ink::env::minimum_balance::<ink::env::DefaultEnvironment>();
+        ink::env::test::set_account_balance::<ink::env::DefaultEnvironment>(
+            contract_id,
+            minimum_balance,
+        );
+        ink::env::test::set_account_balance::<ink::env::DefaultEnvironment>(
+            accounts.bob,
+            100_000_000,
+        );
+        ink::env::test::set_account_balance::<ink::env::DefaultEnvironment>(accounts.django, 0);
+        ink::env::test::set_account_balance::<ink::env::DefaultEnvironment>(accounts.eve, 0);
+        ink::env::test::set_account_balance::<ink::env::DefaultEnvironment>(accounts.frank, 0);
+        let unit_price = params.mint_price + params.mint_fee;
+
+        ink::env::test::set_caller::<ink::env::DefaultEnvironment>(accounts.bob);
+        assert_eq!(
+            ink::env::pay_with_call!(contract.buy_with_referrer(1, accounts.bob), unit_price),
+            Err(PSP34Error::Custom(String::from(
+                "DropspaceSale::buy_with_referrer: Can't refer yourself"
+            )))
+        );
+        assert_eq!(
+            ink::env::pay_with_call!(
+                contract.buy_with_referrer(4, accounts.frank),
+                4 * unit_price
+            ),
+            Ok(())
+        );
+
+        // 5% of the price part, the dev fee is untouched
+        let commission = 4 * params.mint_price * 500 / BPS_DENOMINATOR;
+        assert_eq!(
+            ink::env::test::get_account_balance::<ink::env::DefaultEnvironment>(accounts.django),
+            Ok(4 * params.mint_price - commission)
+        );
+        assert_eq!(
+            ink::env::test::get_account_balance::<ink::env::DefaultEnvironment>(accounts.eve),
+            Ok(4 * params.mint_fee)
+        );
+        assert_eq!(contract.referral_balance(accounts.frank), commission);
+        assert_eq!(contract.referral_totals(accounts.frank), (4, commission));
+
+        // Unclaimed commissions can't be withdrawn by the owner
+        assert_eq!(contract.escrowed(), commission);
+        assert_eq!(contract.withdrawable(), 0);
+
+        ink::env::test::set_caller::<ink::env::DefaultEnvironment>(accounts.frank);
+        assert_eq!(contract.claim_referral_rewards(), Ok(()));
+        assert_eq!(
+            ink::env::test::get_account_balance::<ink::env::DefaultEnvironment>(accounts.frank),
+            Ok(commission)
+        );
+        assert_eq!(contract.referral_balance(accounts.frank), 0);
+        assert_eq!(contract.referral_totals(accounts.frank), (4, commission));
+        assert_eq!(contract.escrowed(), 0);
+        assert_eq!(
+            contract.claim_referral_rewards(),
+            Err(PSP34Error::Custom(String::from(
+                "DropspaceSale::claim_referral_rewards: Nothing to claim"
+            )))
+        );
+    }
+
+    #[ink::test]
+    fn holder_claim_config_works() {
+        let accounts = default_accounts();
+
+        // Set owner
+        ink::env::test::set_caller::<ink::env::DefaultEnvironment>(accounts.charlie);
+
+        let params = ContractParam {
+            withdraw_wallet: Some(accounts.django),
+            dev_wallet: Some(accounts.eve),
+            ..Default::default()
+        };
+        let mut contract = get_contract(&params);
+
+        ink::env::test::set_caller::<ink::env::DefaultEnvironment>(accounts.bob);
+        assert_eq!(
+            contract.holder_claim(vec![Id::U128(1)]),
+            Err(PSP34Error::Custom(String::from(
+                "DropspaceSale::holder_claim: Holder claim not enabled"
+            )))
+        );
+        assert_eq!(
+            contract.set_holder_claim(Some(accounts.frank), 0),
+            Err(PSP34Error::Custom(String::from("O::CallerIsNotOwner")))
+        );
+
+        ink::env::test::set_caller::<ink::env::DefaultEnvironment>(accounts.charlie);
+        assert_eq!(contract.set_holder_claim(Some(accounts.frank), 100), Ok(()));
+        assert_eq!(contract.holder_collection(), Some(accounts.frank));
+        assert_eq!(contract.holder_price(), 100);
+        assert_eq!(contract.holder_claimed(Id::U128(1)), false);
+
+        // Buy checks run before the ownership lookups
+        ink::env::test::set_caller::<ink::env::DefaultEnvironment>(accounts.bob);
+        assert_eq!(
+            contract.holder_claim((0..=params.mint_per_tx).map(Id::U128).collect()),
+            Err(PSP34Error::Custom(String::from(
+                "DropspaceSale::buy: Can't exceed amount of mints per tx"
+            )))
+        );
+        assert_eq!(
+            contract.holder_claim(vec![Id::U128(1)]),
+            Err(PSP34Error::Custom(String::from(
+                "DropspaceSale::holder_claim: Wrong amount paid."
+            )))
+        );
+    }
+
+    #[ink::test]
+    fn redemption_config_works() {
+        let accounts = default_accounts();
+
+        // Set owner
+        ink::env::test::set_caller::<ink::env::DefaultEnvironment>(accounts.charlie);
+
+        let params = ContractParam {
+            supply_limit: 1,
+            withdraw_wallet: Some(accounts.django),
+            dev_wallet: Some(accounts.eve),
+            ..Default::default()
+        };
+        let mut contract = get_contract(&params);
+
+        ink::env::test::set_caller::<ink::env::DefaultEnvironment>(accounts.bob);
+        assert_eq!(
+            contract.redeem(vec![Id::U128(1)]),
+            Err(PSP34Error::Custom(String::from(
+                "DropspaceSale::redeem: Redemption not enabled"
+            )))
+        );
+        assert_eq!(
+            contract.set_redemption(Some(accounts.frank), 1000, 2000),
+            Err(PSP34Error::Custom(String::from("O::CallerIsNotOwner")))
+        );
+
+        ink::env::test::set_caller::<ink::env::DefaultEnvironment>(accounts.charlie);
+        assert_eq!(
+            contract.set_redemption(Some(accounts.frank), 2000, 1000),
+            Err(PSP34Error::Custom(String::from(
+                "DropspaceSale::set_redemption: Window ends before it starts"
+            )))
+        );
+        assert_eq!(
+            contract.set_redemption(Some(accounts.frank), 1000, 2000),
+            Ok(())
+        );
+        assert_eq!(contract.pass_collection(), Some(accounts.frank));
+        assert_eq!(contract.redemption_window(), (1000, 2000));
+        assert_eq!(contract.pass_redeemed(accounts.frank, Id::U128(1)), false);
+
+        ink::env::test::set_caller::<ink::env::DefaultEnvironment>(accounts.bob);
+        for timestamp in [999, 2000] {
+            ink::env::test::set_block_timestamp::<ink::env::DefaultEnvironment>(timestamp);
+            assert_eq!(
+                contract.redeem(vec![Id::U128(1)]),
+                Err(PSP34Error::Custom(String::from(
+                    "DropspaceSale::redeem: Redemption window closed"
+                )))
+            );
+        }
+
+        ink::env::test::set_block_timestamp::<ink::env::DefaultEnvironment>(1500);
+        assert_eq!(
+            contract.redeem(vec![Id::U128(1), Id::U128(2)]),
+            Err(PSP34Error::Custom(String::from(
+                "DropspaceSale::redeem: Supply limit reached"
+            )))
+        );
+    }
+
+    #[ink::test]
+    fn raffle_works() {
+        let accounts = default_accounts();
+
+        // Set owner
+        ink::env::test::set_caller::<ink::env::DefaultEnvironment>(accounts.charlie);
+
+        let params = ContractParam {
+            supply_limit: 3,
+            withdraw_wallet: Some(accounts.eve),
+            dev_wallet: Some(accounts.charlie),
+            ..Default::default()
+        };
+        let mut contract = get_contract(&params);
+        let unit_price = params.mint_price + params.mint_fee;
+
+        let seed = [7u8; 32];
+        let mut seed_hash = <Blake2x256 as HashOutput>::Type::default();
+        ink::env::hash_bytes::<Blake2x256>(&seed, &mut seed_hash);
+        assert_eq!(
+            contract.start_raffle(1000, 2000, 3000, 2, 2, seed_hash),
+            Ok(())
+        );
+        assert_eq!(
+            contract.start_raffle(1000, 2000, 3000, 2, 2, seed_hash),
+            Err(PSP34Error::Custom(String::from(
+                "DropspaceSale::start_raffle: Raffle already started"
+            )))
+        );
+
+        // Raffle supply is set aside from the open sale
+        assert_eq!(
+            contract.reserve(2),
+            Err(PSP34Error::Custom(String::from(
+                "DropspaceSale::reserve: Supply limit reached"
+            )))
+        );
+        assert_eq!(
+            contract.set_supply_limit(1),
+            Err(PSP34Error::Custom(String::from(
+                "DropspaceSale::set_total_supply: Supply limit is lesser than current supply"
+            )))
+        );
+        assert_eq!(
+            contract.update_sale_config(SaleConfigUpdate {
+                supply_limit: Some(1),
+                ..Default::default()
+            }),
+            Err(PSP34Error::Custom(String::from(
+                "DropspaceSale::update_sale_config: Supply limit is lesser than current supply"
+            )))
+        );
+
+        let contract_id = ink::env::account_id::<ink::env::DefaultEnvironment>();
+        let minimum_balance = ink::env::minimum_balance::<ink::env::DefaultEnvironment>();
+        ink::env::test::set_account_balance::<ink::env::DefaultEnvironment>(
+            contract_id,
+            minimum_balance,
+        );
+        let entrants = [(accounts.bob, 2), (accounts.django, 1), (accounts.frank, 1)];
+        for (entrant, _) in entrants {
+            ink::env::test::set_account_balance::<ink::env::DefaultEnvironment>(
+                entrant,
+                100_000_000,
+            );
+        }
+
+        ink::env::test::set_block_timestamp::<ink::env::DefaultEnvironment>(1500);
+        for (entrant, tickets) in entrants {
+            ink::env::test::set_caller::<ink::env::DefaultEnvironment>(entrant);
+            assert_eq!(
+                ink::env::pay_with_call!(
+                    contract.enter_raffle(tickets),
+                    tickets as u128 * unit_price
+                ),
+                Ok(())
+            );
+        }
+        ink::env::test::set_caller::<ink::env::DefaultEnvironment>(accounts.bob);
+        assert_eq!(
+            ink::env::pay_with_call!(contract.enter_raffle(1), unit_price),
+            Err(PSP34Error::Custom(String::from(
+                "DropspaceSale::enter_raffle: Can't exceed amount of tickets per wallet"
+            )))
+        );
+        assert_eq!(contract.escrowed(), 4 * unit_price);
+        assert_eq!(contract.withdrawable(), 0);
+
+        ink::env::test::set_block_timestamp::<ink::env::DefaultEnvironment>(2000);
+        assert_eq!(
+            ink::env::pay_with_call!(contract.enter_raffle(1), unit_price),
+            Err(PSP34Error::Custom(String::from(
+                "DropspaceSale::enter_raffle: Entries are closed"
+            )))
+        );
+        assert_eq!(
+            contract.draw_raffle(10),
+            Err(PSP34Error::Custom(String::from(
+                "DropspaceSale::draw_raffle: Seed not revealed"
+            )))
+        );
+
+        ink::env::test::set_caller::<ink::env::DefaultEnvironment>(accounts.charlie);
+        assert_eq!(
+            contract.reveal_raffle_seed([8u8; 32]),
+            Err(PSP34Error::Custom(String::from(
+                "DropspaceSale::reveal_raffle_seed: Seed doesn't match commitment"
+            )))
+        );
+        ink::env::test::advance_block::<ink::env::DefaultEnvironment>();
+        assert_eq!(contract.reveal_raffle_seed(seed), Ok(()));
+
+        // The reveal block doesn't affect the draw
+        let raffle = contract.raffle().unwrap_or_default();
+        let mut entropy = <Blake2x256 as HashOutput>::Type::default();
+        ink::env::hash_encoded::<Blake2x256, _>(&(seed, raffle.entries_hash), &mut entropy);
+        assert_ne!(raffle.entries_hash, [0u8; 32]);
+        assert_eq!(raffle.entropy, Some(entropy));
+
+        // Winners are drawn in batches
+        assert_eq!(contract.draw_raffle(1), Ok(()));
+        ink::env::test::set_caller::<ink::env::DefaultEnvironment>(accounts.bob);
+        assert_eq!(
+            contract.claim_raffle(),
+            Err(PSP34Error::Custom(String::from(
+                "DropspaceSale::raffle: Draw not complete"
+            )))
+        );
+        assert_eq!(contract.draw_raffle(10), Ok(()));
+        assert_eq!(
+            contract.draw_raffle(10),
+            Err(PSP34Error::Custom(String::from(
+                "DropspaceSale::draw_raffle: Draw already complete"
+            )))
+        );
+        assert_eq!(contract.raffle().map(|raffle| raffle.drawn), Some(2));
+
+        let total_wins: u32 = entrants
+            .iter()
+            .map(|(entrant, _)| contract.raffle_entry(*entrant).wins)
+            .sum();
+        assert_eq!(total_wins, 2);
+
+        for (entrant, tickets) in entrants {
+            let wins = contract.raffle_entry(entrant).wins;
+            ink::env::test::set_caller::<ink::env::DefaultEnvironment>(entrant);
+            if wins > 0 {
+                assert_eq!(contract.claim_raffle(), Ok(()));
+                assert_eq!(psp34::PSP34::balance_of(&contract, entrant), wins);
+            }
+            if tickets > wins {
+                assert_eq!(contract.refund_raffle(), Ok(()));
+                assert_eq!(
+                    contract.refund_raffle(),
+                    Err(PSP34Error::Custom(String::from(
+                        "DropspaceSale::refund_raffle: Nothing to refund"
+                    )))
+                );
+            }
+            assert_eq!(
+                ink::env::test::get_account_balance::<ink::env::DefaultEnvironment>(entrant),
+                Ok(100_000_000 - wins as u128 * unit_price)
+            );
         }
+
+        assert_eq!(psp34::PSP34::total_supply(&contract), 2);
+        assert_eq!(contract.escrowed(), 0);
+        assert_eq!(contract.get_account_balance(), minimum_balance);
+        assert_eq!(
+            contract.raised(PaymentAsset::Native).0,
+            2 * params.mint_price
+        );
     }
-}
 
-#[cfg(test)]
-mod tests {
-    #[rustfmt::skip]
-    use super::*;
-    use dropspace_sale::Contract;
-    use ink::{env::DefaultEnvironment as Environment, primitives::AccountId};
-    use openbrush::contracts::ownable::Ownable;
-    use openbrush::contracts::psp34::extensions::metadata::psp34metadata_external::PSP34Metadata;
-    use openbrush::contracts::psp34::{psp34, Id, PSP34Error};
+    #[ink::test]
+    fn raffle_reveal_deadline_works() {
+        let accounts = default_accounts();
 
-    fn default_accounts() -> ink::env::test::DefaultAccounts<ink::env::DefaultEnvironment> {
-        ink::env::test::default_accounts::<ink::env::DefaultEnvironment>()
+        // Set owner
+        ink::env::test::set_caller::<ink::env::DefaultEnvironment>(accounts.charlie);
+
+        let params = ContractParam {
+            supply_limit: 1,
+            withdraw_wallet: Some(accounts.eve),
+            dev_wallet: Some(accounts.charlie),
+            ..Default::default()
+        };
+        let mut contract = get_contract(&params);
+        let unit_price = params.mint_price + params.mint_fee;
+
+        let seed = [7u8; 32];
+        let mut seed_hash = <Blake2x256 as HashOutput>::Type::default();
+        ink::env::hash_bytes::<Blake2x256>(&seed, &mut seed_hash);
+        assert_eq!(
+            contract.start_raffle(1000, 2000, 1500, 1, 2, seed_hash),
+            Err(PSP34Error::Custom(String::from(
+                "DropspaceSale::start_raffle: Reveal deadline before entries close"
+            )))
+        );
+        assert_eq!(
+            contract.start_raffle(1000, 2000, 3000, 1, 2, seed_hash),
+            Ok(())
+        );
+
+        let contract_id = ink::env::account_id::<ink::env::DefaultEnvironment>();
+        let minimum_balance = ink::env::minimum_balance::<ink::env::DefaultEnvironment>();
+        ink::env::test::set_account_balance::<ink::env::DefaultEnvironment>(
+            contract_id,
+            minimum_balance,
+        );
+        ink::env::test::set_account_balance::<ink::env::DefaultEnvironment>(
+            accounts.bob,
+            100_000_000,
+        );
+        ink::env::test::set_block_timestamp::<ink::env::DefaultEnvironment>(1500);
+        ink::env::test::set_caller::<ink::env::DefaultEnvironment>(accounts.bob);
+        assert_eq!(
+            ink::env::pay_with_call!(contract.enter_raffle(2), 2 * unit_price),
+            Ok(())
+        );
+
+        ink::env::test::set_block_timestamp::<ink::env::DefaultEnvironment>(2500);
+        assert_eq!(
+            contract.refund_raffle(),
+            Err(PSP34Error::Custom(String::from(
+                "DropspaceSale::raffle: Draw not complete"
+            )))
+        );
+
+        // The owner missed the deadline, the draw can't happen anymore
+        ink::env::test::set_block_timestamp::<ink::env::DefaultEnvironment>(3000);
+        ink::env::test::set_caller::<ink::env::DefaultEnvironment>(accounts.charlie);
+        assert_eq!(
+            contract.reveal_raffle_seed(seed),
+            Err(PSP34Error::Custom(String::from(
+                "DropspaceSale::reveal_raffle_seed: Reveal deadline passed"
+            )))
+        );
+        assert_eq!(
+            contract.reserve(1),
+            Err(PSP34Error::Custom(String::from(
+                "DropspaceSale::reserve: Supply limit reached"
+            )))
+        );
+
+        ink::env::test::set_caller::<ink::env::DefaultEnvironment>(accounts.bob);
+        assert_eq!(contract.refund_raffle(), Ok(()));
+        assert_eq!(
+            contract.refund_raffle(),
+            Err(PSP34Error::Custom(String::from(
+                "DropspaceSale::refund_raffle: Nothing to refund"
+            )))
+        );
+        assert_eq!(
+            ink::env::test::get_account_balance::<ink::env::DefaultEnvironment>(accounts.bob),
+            Ok(100_000_000)
+        );
+        assert_eq!(contract.escrowed(), 0);
+
+        // The raffle's slot is back in the open sale
+        ink::env::test::set_caller::<ink::env::DefaultEnvironment>(accounts.charlie);
+        assert_eq!(contract.reserve(1), Ok(()));
     }
 
-    struct ContractParam {
-        name: String,
-        symbol: String,
-        base_uri: String,
-        supply_limit: u128,
-        mint_per_tx: u128,
-        mint_price: u128,
-        mint_fee: u128,
-        withdraw_wallet: Option<AccountId>,
-        dev_wallet: Option<AccountId>,
-        sale_time: u64,
+    #[ink::test]
+    fn raffle_without_entrants_works() {
+        let accounts = default_accounts();
+
+        // Set owner
+        ink::env::test::set_caller::<ink::env::DefaultEnvironment>(accounts.charlie);
+
+        let params = ContractParam {
+            supply_limit: 1,
+            withdraw_wallet: Some(accounts.eve),
+            dev_wallet: Some(accounts.charlie),
+            ..Default::default()
+        };
+        let mut contract = get_contract(&params);
+
+        let mut seed_hash = <Blake2x256 as HashOutput>::Type::default();
+        ink::env::hash_bytes::<Blake2x256>(&[7u8; 32], &mut seed_hash);
+        assert_eq!(
+            contract.start_raffle(1000, 2000, 3000, 1, 2, seed_hash),
+            Ok(())
+        );
+
+        // Nobody entered and the seed was never revealed
+        ink::env::test::set_block_timestamp::<ink::env::DefaultEnvironment>(2500);
+        assert_eq!(
+            contract.draw_raffle(1),
+            Err(PSP34Error::Custom(String::from(
+                "DropspaceSale::draw_raffle: Seed not revealed"
+            )))
+        );
+        assert_eq!(
+            contract.reserve(1),
+            Err(PSP34Error::Custom(String::from(
+                "DropspaceSale::reserve: Supply limit reached"
+            )))
+        );
+
+        // Anyone can release the slot once the deadline passed
+        ink::env::test::set_block_timestamp::<ink::env::DefaultEnvironment>(3000);
+        ink::env::test::set_caller::<ink::env::DefaultEnvironment>(accounts.bob);
+        assert_eq!(contract.draw_raffle(1), Ok(()));
+        assert_eq!(contract.raffle().map(|raffle| raffle.abandoned), Some(true));
+        assert_eq!(
+            contract.draw_raffle(1),
+            Err(PSP34Error::Custom(String::from(
+                "DropspaceSale::draw_raffle: Raffle abandoned"
+            )))
+        );
+
+        ink::env::test::set_caller::<ink::env::DefaultEnvironment>(accounts.charlie);
+        assert_eq!(contract.reserve(1), Ok(()));
     }
 
-    impl Default for ContractParam {
-        fn default() -> ContractParam {
-            ContractParam {
-                name: "Test".to_string(),
-                symbol: "TST".to_string(),
-                base_uri: "https://example.com/token/".to_string(),
-                supply_limit: 100000,
-                mint_per_tx: 10,
-                mint_price: 1000,
-                mint_fee: 10,
-                withdraw_wallet: None,
-                dev_wallet: None,
-                sale_time: 0,
-            }
-        }
+    #[ink::test]
+    fn auction_and_raffle_reservations_works() {
+        let accounts = default_accounts();
+
+        // Set owner
+        ink::env::test::set_caller::<ink::env::DefaultEnvironment>(accounts.charlie);
+
+        let params = ContractParam {
+            supply_limit: 3,
+            withdraw_wallet: Some(accounts.eve),
+            dev_wallet: Some(accounts.charlie),
+            ..Default::default()
+        };
+        let mut contract = get_contract(&params);
+
+        let seed = [7u8; 32];
+        let mut seed_hash = <Blake2x256 as HashOutput>::Type::default();
+        ink::env::hash_bytes::<Blake2x256>(&seed, &mut seed_hash);
+        assert_eq!(
+            contract.start_auction(Id::U128(100), 0, 1000, 100, 10, 0),
+            Ok(())
+        );
+        assert_eq!(
+            contract.start_raffle(1000, 2000, 3000, 2, 2, seed_hash),
+            Ok(())
+        );
+
+        // The raffle's slots come on top of the auction's
+        assert_eq!(
+            contract.reserve(1),
+            Err(PSP34Error::Custom(String::from(
+                "DropspaceSale::reserve: Supply limit reached"
+            )))
+        );
+
+        // Reserved slots can't be cut from under the raffle and auction either
+        assert_eq!(
+            contract.set_supply_limit(2),
+            Err(PSP34Error::Custom(String::from(
+                "DropspaceSale::set_total_supply: Supply limit is lesser than current supply"
+            )))
+        );
+        assert_eq!(
+            contract.update_sale_config(SaleConfigUpdate {
+                supply_limit: Some(2),
+                ..Default::default()
+            }),
+            Err(PSP34Error::Custom(String::from(
+                "DropspaceSale::update_sale_config: Supply limit is lesser than current supply"
+            )))
+        );
+
+        let contract_id = ink::env::account_id::<ink::env::DefaultEnvironment>();
+        let minimum_balance = ink::env::minimum_balance::<ink::env::DefaultEnvironment>();
+        ink::env::test::set_account_balance::<ink::env::DefaultEnvironment>(
+            contract_id,
+            minimum_balance,
+        );
+        ink::env::test::set_account_balance::<ink::env::DefaultEnvironment>(
+            accounts.bob,
+            100_000_000,
+        );
+        ink::env::test::set_block_timestamp::<ink::env::DefaultEnvironment>(1500);
+        ink::env::test::set_caller::<ink::env::DefaultEnvironment>(accounts.bob);
+        assert_eq!(
+            ink::env::pay_with_call!(
+                contract.enter_raffle(1),
+                params.mint_price + params.mint_fee
+            ),
+            Ok(())
+        );
+
+        // Only the raffle slot nobody entered for is freed, the auction keeps its own
+        ink::env::test::set_block_timestamp::<ink::env::DefaultEnvironment>(2000);
+        ink::env::test::set_caller::<ink::env::DefaultEnvironment>(accounts.charlie);
+        assert_eq!(contract.reveal_raffle_seed(seed), Ok(()));
+        assert_eq!(contract.reserve(1), Ok(()));
+        assert_eq!(
+            contract.reserve(1),
+            Err(PSP34Error::Custom(String::from(
+                "DropspaceSale::reserve: Supply limit reached"
+            )))
+        );
     }
 
-    fn get_contract(args: &ContractParam) -> Contract {
-        return Contract::new(
-            args.name.to_string(),
-            args.symbol.to_string(),
-            args.base_uri.to_string(),
-            args.mint_per_tx,
-            args.mint_price,
-            args.mint_fee,
-            args.supply_limit,
-            args.withdraw_wallet,
-            args.dev_wallet,
-            args.sale_time,
+    #[ink::test]
+    fn auction_works() {
+        let accounts = default_accounts();
+
+        // Set owner
+        ink::env::test::set_caller::<ink::env::DefaultEnvironment>(accounts.charlie);
+
+        let params = ContractParam {
+            withdraw_wallet: Some(accounts.eve),
+            dev_wallet: Some(accounts.charlie),
+            ..Default::default()
+        };
+        let mut contract = get_contract(&params);
+        let id = Id::U128(1);
+
+        assert_eq!(
+            contract.start_auction(id.clone(), 1000, 2000, 5000, 100, 300),
+            Ok(())
+        );
+        assert_eq!(
+            contract.start_auction(id.clone(), 1000, 2000, 5000, 100, 300),
+            Err(PSP34Error::Custom(String::from(
+                "DropspaceSale::start_auction: Token already auctioned"
+            )))
+        );
+
+        // Sequential mints skip the auctioned id
+        assert_eq!(contract.reserve(3), Ok(()));
+        for minted in [0, 2, 3] {
+            assert_eq!(
+                psp34::PSP34::owner_of(&contract, Id::U128(minted)),
+                Some(accounts.charlie)
+            );
+        }
+        assert_eq!(psp34::PSP34::owner_of(&contract, id.clone()), None);
+
+        // Burned and non-sequential ids can't be auctioned
+        assert_eq!(
+            psp34::Internal::_burn_from(&mut contract, accounts.charlie, Id::U128(0)),
+            Ok(())
+        );
+        for taken in [Id::U128(0), Id::U128(2), Id::U8(100)] {
+            assert_eq!(
+                contract.start_auction(taken, 1000, 2000, 5000, 100, 300),
+                Err(PSP34Error::Custom(String::from(
+                    "DropspaceSale::start_auction: Token already minted"
+                )))
+            );
+        }
+
+        let contract_id = ink::env::account_id::<ink::env::DefaultEnvironment>();
+        let minimum_balance = ink::env::minimum_balance::<ink::env::DefaultEnvironment>();
+        ink::env::test::set_account_balance::<ink::env::DefaultEnvironment>(
+            contract_id,
+            minimum_balance,
+        );
+        for bidder in [accounts.bob, accounts.frank] {
+            ink::env::test::set_account_balance::<ink::env::DefaultEnvironment>(
+                bidder,
+                100_000_000,
+            );
+        }
+
+        ink::env::test::set_caller::<ink::env::DefaultEnvironment>(accounts.bob);
+        assert_eq!(
+            ink::env::pay_with_call!(contract.bid(id.clone()), 5000),
+            Err(PSP34Error::Custom(String::from(
+                "DropspaceSale::bid: Auction isn't running"
+            )))
+        );
+
+        ink::env::test::set_block_timestamp::<ink::env::DefaultEnvironment>(1000);
+        assert_eq!(
+            ink::env::pay_with_call!(contract.bid(id.clone()), 4000),
+            Err(PSP34Error::Custom(String::from(
+                "DropspaceSale::bid: Bid below reserve price"
+            )))
+        );
+        assert_eq!(
+            ink::env::pay_with_call!(contract.bid(id.clone()), 5000),
+            Ok(())
+        );
+
+        ink::env::test::set_caller::<ink::env::DefaultEnvironment>(accounts.frank);
+        assert_eq!(
+            ink::env::pay_with_call!(contract.bid(id.clone()), 5050),
+            Err(PSP34Error::Custom(String::from(
+                "DropspaceSale::bid: Bid increment too low"
+            )))
+        );
+        assert_eq!(
+            ink::env::pay_with_call!(contract.bid(id.clone()), 5100),
+            Ok(())
+        );
+
+        // Bob was outbid and got the bid back
+        assert_eq!(
+            ink::env::test::get_account_balance::<ink::env::DefaultEnvironment>(accounts.bob),
+            Ok(100_000_000)
+        );
+        assert_eq!(contract.escrowed(), 5100);
+        assert_eq!(contract.withdrawable(), 0);
+
+        // A late bid pushes the end back
+        ink::env::test::set_block_timestamp::<ink::env::DefaultEnvironment>(1800);
+        ink::env::test::set_caller::<ink::env::DefaultEnvironment>(accounts.bob);
+        assert_eq!(
+            ink::env::pay_with_call!(contract.bid(id.clone()), 5200),
+            Ok(())
+        );
+        assert_eq!(
+            contract.auction(id.clone()).map(|auction| auction.end_time),
+            Some(2100)
+        );
+        assert_eq!(
+            ink::env::test::get_account_balance::<ink::env::DefaultEnvironment>(accounts.frank),
+            Ok(100_000_000)
+        );
+
+        ink::env::test::set_block_timestamp::<ink::env::DefaultEnvironment>(2000);
+        assert_eq!(
+            contract.settle_auction(id.clone()),
+            Err(PSP34Error::Custom(String::from(
+                "DropspaceSale::settle_auction: Auction hasn't ended yet"
+            )))
+        );
+
+        ink::env::test::set_block_timestamp::<ink::env::DefaultEnvironment>(2100);
+        assert_eq!(contract.settle_auction(id.clone()), Ok(()));
+        assert_eq!(
+            contract.settle_auction(id.clone()),
+            Err(PSP34Error::Custom(String::from(
+                "DropspaceSale::auction: No auction running for token"
+            )))
+        );
+
+        assert_eq!(
+            psp34::PSP34::owner_of(&contract, id.clone()),
+            Some(accounts.bob)
+        );
+        assert_eq!(psp34::PSP34::total_supply(&contract), 3);
+        assert_eq!(contract.sale_stats().tokens_sold, 1);
+        assert_eq!(
+            contract.raised(PaymentAsset::Native),
+            (5200 - params.mint_fee, params.mint_fee)
         );
+        assert_eq!(contract.escrowed(), 0);
+        assert_eq!(contract.get_account_balance(), minimum_balance);
     }
 
     #[ink::test]
-    fn new_works() {
+    fn tiers_works() {
         let accounts = default_accounts();
 
+        // Set owner
+        ink::env::test::set_caller::<ink::env::DefaultEnvironment>(accounts.charlie);
+
         let params = ContractParam {
+            supply_limit: 4,
             withdraw_wallet: Some(accounts.django),
-            dev_wallet: Some(accounts.alice),
+            dev_wallet: Some(accounts.eve),
             ..Default::default()
         };
-        let contract = get_contract(&params);
+        let mut contract = get_contract(&params);
 
-        assert_eq!(contract.supply_limit(), params.supply_limit);
-        assert_eq!(contract.mint_per_tx(), params.mint_per_tx);
-        assert_eq!(contract.mint_price(), params.mint_price);
-        assert_eq!(contract.mint_fee(), params.mint_fee);
-        assert_eq!(contract.dev_wallet(), params.dev_wallet);
-        assert_eq!(contract.withdraw_wallet(), params.withdraw_wallet);
-        assert_eq!(contract.sale_time(), params.sale_time);
-        assert_eq!(contract.sale_active(), true);
+        assert_eq!(
+            contract.add_tier(
+                "Common".to_string(),
+                10,
+                1000,
+                10,
+                "https://example.com/common/".to_string()
+            ),
+            Ok(0)
+        );
+        assert_eq!(
+            contract.add_tier(
+                "Legendary".to_string(),
+                1,
+                5000,
+                50,
+                "https://example.com/legendary/".to_string()
+            ),
+            Ok(1)
+        );
+        assert_eq!(contract.tier_count(), 2);
+        assert_eq!(contract.reserve(1), Ok(()));
 
-        let id = psp34::PSP34::collection_id(&contract);
+        ink::env::test::set_account_balance::<ink::env::DefaultEnvironment>(
+            accounts.bob,
+            100_000_000,
+        );
+        ink::env::test::set_caller::<ink::env::DefaultEnvironment>(accounts.bob);
         assert_eq!(
-            PSP34Metadata::get_attribute(&contract, id.clone(), String::from("name")),
-            Some(params.name)
+            ink::env::pay_with_call!(contract.buy_tier(2, 1), 1010),
+            Err(PSP34Error::Custom(String::from(
+                "DropspaceSale::buy_tier: Tier not found"
+            )))
         );
         assert_eq!(
-            PSP34Metadata::get_attribute(&contract, id.clone(), String::from("symbol")),
-            Some(params.symbol)
+            ink::env::pay_with_call!(contract.buy_tier(1, 1), 1010),
+            Err(PSP34Error::Custom(String::from(
+                "DropspaceSale::buy_tier: Wrong amount paid."
+            )))
+        );
+        assert_eq!(
+            ink::env::pay_with_call!(contract.buy_tier(1, 1), 5050),
+            Ok(())
+        );
+        assert_eq!(
+            ink::env::pay_with_call!(contract.buy_tier(1, 1), 5050),
+            Err(PSP34Error::Custom(String::from(
+                "DropspaceSale::buy_tier: Tier supply limit reached"
+            )))
+        );
+        assert_eq!(
+            ink::env::pay_with_call!(contract.buy_tier(0, 2), 2020),
+            Ok(())
         );
-        assert_eq!(contract.base_uri(), params.base_uri);
-    }
 
-    #[ink::test]
-    fn reserve_works() {
-        let accounts = default_accounts();
-        let mut contract = Contract::new(
-            "Test".to_string(),
-            "TST".to_string(),
-            "https://example.com/token/".to_string(),
-            10,
-            1000,
-            10,
-            100000,
-            Some(accounts.django),
-            Some(accounts.alice),
-            12345678,
+        // The global supply limit still caps every tier
+        assert_eq!(
+            ink::env::pay_with_call!(contract.buy_tier(0, 1), 1010),
+            Err(PSP34Error::Custom(String::from(
+                "DropspaceSale::buy: Supply limit reached"
+            )))
         );
 
-        assert_eq!(contract.reserve(5), Ok(()));
-        assert_eq!(psp34::PSP34::total_supply(&contract), 5);
+        assert_eq!(contract.token_tier(0), None);
+        assert_eq!(contract.token_tier(1), Some(1));
+        assert_eq!(contract.token_tier(2), Some(0));
         assert_eq!(
-            contract.reserve(100001),
+            contract.token_uri(0),
+            Ok("https://example.com/token/0".to_string())
+        );
+        assert_eq!(
+            contract.token_uri(1),
+            Ok("https://example.com/legendary/1".to_string())
+        );
+        assert_eq!(
+            contract.token_uri(3),
+            Ok("https://example.com/common/3".to_string())
+        );
+        assert_eq!(contract.tier(0).map(|tier| tier.minted), Some(2));
+        assert_eq!(contract.sale_stats().tokens_sold, 3);
+        assert_eq!(contract.raised(PaymentAsset::Native), (7000, 70));
+
+        ink::env::test::set_caller::<ink::env::DefaultEnvironment>(accounts.charlie);
+        assert_eq!(
+            contract.update_tier(0, 1, 1000, 10, "https://example.com/common/".to_string()),
             Err(PSP34Error::Custom(String::from(
-                "DropspaceSale::reserve: Supply limit reached"
+                "DropspaceSale::update_tier: Supply limit below minted"
             )))
         );
     }
 
     #[ink::test]
-    fn buy_works() {
+    fn crowdfund_refund_works() {
         let accounts = default_accounts();
 
         // Set owner
@@ -419,143 +5143,299 @@ mod tests {
             ..Default::default()
         };
         let mut contract = get_contract(&params);
-        assert_eq!(contract.get_account_balance(), 1000000);
-
-        // current owner checking
-        assert_eq!(Ownable::owner(&contract), Some(accounts.charlie));
-
-        ink::env::debug_println!(" Dev Fee={:?}", contract.mint_fee());
-        ink::env::debug_println!(" Contract balance={:?}\n", contract.get_account_balance());
+        assert_eq!(contract.set_crowdfund(5000, 3, 2000), Ok(()));
 
+        let contract_id = ink::env::account_id::<ink::env::DefaultEnvironment>();
+        let minimum_balance = ink::env::minimum_balance::<ink::env::DefaultEnvironment>();
+        ink::env::test::set_account_balance::<ink::env::DefaultEnvironment>(
+            contract_id,
+            minimum_balance,
+        );
+        ink::env::test::set_account_balance::<ink::env::DefaultEnvironment>(accounts.django, 0);
         ink::env::test::set_account_balance::<ink::env::DefaultEnvironment>(
             accounts.bob,
             100_000_000,
         );
-        ink::env::test::set_account_balance::<ink::env::DefaultEnvironment>(accounts.django, 0);
-        ink::env::test::set_account_balance::<ink::env::DefaultEnvironment>(accounts.eve, 0);
-        ink::env::debug_println!(
-            " Withdraw Wallet (django) bal={:?}",
-            ink::env::test::get_account_balance::<ink::env::DefaultEnvironment>(accounts.django)
+
+        // Proceeds stay in the contract
+        ink::env::test::set_caller::<ink::env::DefaultEnvironment>(accounts.bob);
+        assert_eq!(ink::env::pay_with_call!(contract.buy(2), 2020), Ok(()));
+        assert_eq!(
+            ink::env::test::get_account_balance::<ink::env::DefaultEnvironment>(accounts.django),
+            Ok(0)
         );
-        ink::env::debug_println!(
-            " Dev Wallet (eve) bal={:?}",
-            ink::env::test::get_account_balance::<ink::env::DefaultEnvironment>(accounts.eve)
+        assert_eq!(
+            contract.token_payment(1),
+            Some(Payment {
+                paid_at: 0,
+                price: 1000,
+                fee: 10,
+                refundable_until: 0,
+            })
+        );
+        assert_eq!(contract.escrowed(), 2020);
+        assert_eq!(contract.withdrawable(), 0);
+        assert_eq!(
+            ink::env::pay_with_call!(contract.buy_with_referrer(1, accounts.frank), 1010),
+            Err(PSP34Error::Custom(String::from(
+                "DropspaceSale::buy_with_referrer: Referrals not available for refundable purchases"
+            )))
+        );
+        assert_eq!(
+            contract.buy_with_psp22(accounts.frank, 1),
+            Err(PSP34Error::Custom(String::from(
+                "DropspaceSale::buy_with_psp22: Not available while crowdfunding"
+            )))
+        );
+        assert_eq!(
+            contract
+                .quote(PaymentAsset::Psp22(accounts.frank), 1, accounts.bob)
+                .rejection,
+            Some(PSP34Error::Custom(String::from(
+                "DropspaceSale::buy_with_psp22: Not available while crowdfunding"
+            )))
+        );
+        assert_eq!(
+            contract.holder_claim(vec![Id::U128(1)]),
+            Err(PSP34Error::Custom(String::from(
+                "DropspaceSale::holder_claim: Not available while crowdfunding"
+            )))
+        );
+        assert_eq!(
+            contract.refund(vec![0, 1]),
+            Err(PSP34Error::Custom(String::from(
+                "DropspaceSale::refund: Refunds not available"
+            )))
         );
 
-        let qty = 7;
-        let required_value = qty * (params.mint_price + params.mint_fee);
+        ink::env::test::set_block_timestamp::<ink::env::DefaultEnvironment>(2000);
+        assert_eq!(contract.sale_active(), false);
+        assert_eq!(
+            ink::env::pay_with_call!(contract.buy(1), 1010),
+            Err(PSP34Error::Custom(String::from(
+                "DropspaceSale::buy: Sale has ended"
+            )))
+        );
+        assert_eq!(contract.funding_reached(), false);
 
-        // Setting the caller for the next contract call
-        ink::env::test::set_caller::<ink::env::DefaultEnvironment>(accounts.bob);
+        ink::env::test::set_caller::<ink::env::DefaultEnvironment>(accounts.charlie);
+        assert_eq!(
+            contract.release_funds(),
+            Err(PSP34Error::Custom(String::from(
+                "DropspaceSale::release_funds: Funds can't be released"
+            )))
+        );
 
+        ink::env::test::set_caller::<ink::env::DefaultEnvironment>(accounts.bob);
+        assert_eq!(contract.refund(vec![0, 1]), Ok(()));
         assert_eq!(
-            ink::env::pay_with_call!(contract.buy(qty), required_value),
-            Ok(())
+            contract.refund(vec![0]),
+            Err(PSP34Error::Custom(String::from(
+                "DropspaceSale::refund: Token not refundable"
+            )))
+        );
+        assert_eq!(
+            ink::env::test::get_account_balance::<ink::env::DefaultEnvironment>(accounts.bob),
+            Ok(100_000_000)
         );
+        assert_eq!(psp34::PSP34::total_supply(&contract), 0);
+        assert_eq!(contract.refunded(PaymentAsset::Native), 2020);
+        assert_eq!(contract.escrowed(), 0);
+        assert_eq!(contract.get_account_balance(), minimum_balance);
+    }
 
-        assert_eq!(psp34::PSP34::total_supply(&contract), qty);
+    #[ink::test]
+    fn refund_does_not_reuse_ids_works() {
+        let accounts = default_accounts();
 
+        // Set owner
+        ink::env::test::set_caller::<ink::env::DefaultEnvironment>(accounts.charlie);
+
+        let params = ContractParam {
+            withdraw_wallet: Some(accounts.django),
+            dev_wallet: Some(accounts.eve),
+            ..Default::default()
+        };
+        let mut contract = get_contract(&params);
+        assert_eq!(contract.set_crowdfund(5000, 3, 2000), Ok(()));
         assert_eq!(
-            psp34::PSP34::owner_of(&contract, Id::U128(1)),
-            Some(accounts.bob)
+            contract.add_tier(
+                "Rare".to_string(),
+                1,
+                2000,
+                20,
+                "https://example.com/rare/".to_string()
+            ),
+            Ok(0)
         );
-        assert_eq!(psp34::PSP34::owner_of(&contract, Id::U128(qty)), None); // invalid token's ownership test
 
-        // Checking splitted balances
-        ink::env::debug_println!(
-            "\n Withdraw Wallet (django) bal={:?}",
-            ink::env::test::get_account_balance::<ink::env::DefaultEnvironment>(accounts.django)
+        let contract_id = ink::env::account_id::<ink::env::DefaultEnvironment>();
+        let minimum_balance = ink::env::minimum_balance::<ink::env::DefaultEnvironment>();
+        ink::env::test::set_account_balance::<ink::env::DefaultEnvironment>(
+            contract_id,
+            minimum_balance,
         );
-        ink::env::debug_println!(
-            " Dev Wallet (chalie) bal={:?}",
-            ink::env::test::get_account_balance::<ink::env::DefaultEnvironment>(accounts.eve)
+        ink::env::test::set_account_balance::<ink::env::DefaultEnvironment>(
+            accounts.bob,
+            100_000_000,
         );
-        ink::env::debug_println!(
-            " User Wallet (bob) bal={:?}",
-            ink::env::test::get_account_balance::<ink::env::DefaultEnvironment>(accounts.bob)
+
+        ink::env::test::set_caller::<ink::env::DefaultEnvironment>(accounts.bob);
+        assert_eq!(
+            ink::env::pay_with_call!(contract.buy_tier(0, 1), 2020),
+            Ok(())
         );
+        ink::env::test::set_block_timestamp::<ink::env::DefaultEnvironment>(2000);
+        assert_eq!(contract.refund(vec![0]), Ok(()));
+
+        // The refunded tier slot is free again
+        assert_eq!(contract.token_tier(0), None);
+        assert_eq!(contract.tier(0).map(|tier| tier.minted), Some(0));
+
+        // The next mint gets a fresh id instead of the burned tier one
+        ink::env::test::set_caller::<ink::env::DefaultEnvironment>(accounts.charlie);
+        assert_eq!(contract.reserve(1), Ok(()));
+        assert_eq!(psp34::PSP34::owner_of(&contract, Id::U128(0)), None);
         assert_eq!(
-            ink::env::test::get_account_balance::<ink::env::DefaultEnvironment>(accounts.bob),
-            Ok(100_000_000 - required_value)
+            psp34::PSP34::owner_of(&contract, Id::U128(1)),
+            Some(accounts.charlie)
         );
+        assert_eq!(contract.token_tier(1), None);
         assert_eq!(
-            ink::env::test::get_account_balance::<ink::env::DefaultEnvironment>(accounts.django),
-            Ok(qty * params.mint_price)
+            contract.token_uri(1),
+            Ok("https://example.com/token/1".to_string())
         );
+    }
 
-        let __dev_bal: Result<u128, ink::env::Error> =
-            ink::env::test::get_account_balance::<ink::env::DefaultEnvironment>(accounts.eve);
-        assert_eq!(__dev_bal, Ok(qty * params.mint_fee));
-        ink::env::debug_println!(" Dev Wallet (eve) bal={:?}", __dev_bal);
-        ink::env::debug_println!(" Contract balance={:?}\n", contract.get_account_balance());
+    #[ink::test]
+    fn crowdfund_release_works() {
+        let accounts = default_accounts();
 
-        // Transfer test
+        // Set owner
+        ink::env::test::set_caller::<ink::env::DefaultEnvironment>(accounts.charlie);
+
+        let params = ContractParam {
+            withdraw_wallet: Some(accounts.django),
+            dev_wallet: Some(accounts.eve),
+            ..Default::default()
+        };
+        let mut contract = get_contract(&params);
+        assert_eq!(contract.set_crowdfund(2000, 2, 2000), Ok(()));
         assert_eq!(
-            psp34::PSP34::owner_of(&contract, Id::U128(0)),
-            Some(accounts.bob)
+            contract.start_auction(Id::U128(100), 0, 1000, 100, 10, 0),
+            Err(PSP34Error::Custom(String::from(
+                "DropspaceSale::start_auction: Not available while crowdfunding"
+            )))
         );
-
         assert_eq!(
-            psp34::PSP34::transfer(&mut contract, accounts.frank, Id::U128(0), Vec::new()),
-            Ok(())
+            contract.start_raffle(0, 1000, 2000, 1, 1, [0u8; 32]),
+            Err(PSP34Error::Custom(String::from(
+                "DropspaceSale::start_raffle: Not available while crowdfunding"
+            )))
         );
 
-        assert_eq!(
-            psp34::PSP34::owner_of(&contract, Id::U128(0)),
-            Some(accounts.frank)
+        let contract_id = ink::env::account_id::<ink::env::DefaultEnvironment>();
+        let minimum_balance = ink::env::minimum_balance::<ink::env::DefaultEnvironment>();
+        ink::env::test::set_account_balance::<ink::env::DefaultEnvironment>(
+            contract_id,
+            minimum_balance,
+        );
+        ink::env::test::set_account_balance::<ink::env::DefaultEnvironment>(accounts.django, 0);
+        ink::env::test::set_account_balance::<ink::env::DefaultEnvironment>(accounts.eve, 0);
+        ink::env::test::set_account_balance::<ink::env::DefaultEnvironment>(
+            accounts.bob,
+            100_000_000,
         );
 
-        // Invalid Buy
+        ink::env::test::set_caller::<ink::env::DefaultEnvironment>(accounts.bob);
+        assert_eq!(ink::env::pay_with_call!(contract.buy(2), 2020), Ok(()));
+        assert_eq!(contract.funding_reached(), true);
+
+        ink::env::test::set_caller::<ink::env::DefaultEnvironment>(accounts.charlie);
         assert_eq!(
-            contract.buy(100001),
+            contract.set_crowdfund(1000, 1, 3000),
             Err(PSP34Error::Custom(String::from(
-                "DropspaceSale::buy: Supply limit reached"
+                "DropspaceSale::set_crowdfund: Crowdfund already has sales"
             )))
         );
         assert_eq!(
-            contract.buy(15),
+            contract.release_funds(),
             Err(PSP34Error::Custom(String::from(
-                "DropspaceSale::buy: Can't exceed amount of mints per tx"
+                "DropspaceSale::release_funds: Funds can't be released"
             )))
         );
+
+        ink::env::test::set_block_timestamp::<ink::env::DefaultEnvironment>(2000);
+        assert_eq!(contract.release_funds(), Ok(()));
+        assert_eq!(contract.funds_released(), true);
         assert_eq!(
-            ink::env::pay_with_call!(
-                contract.buy(4),
-                4 * (params.mint_fee + params.mint_price) - 1
-            ),
+            ink::env::test::get_account_balance::<ink::env::DefaultEnvironment>(accounts.django),
+            Ok(2000)
+        );
+        assert_eq!(
+            ink::env::test::get_account_balance::<ink::env::DefaultEnvironment>(accounts.eve),
+            Ok(20)
+        );
+        assert_eq!(contract.escrowed(), 0);
+
+        ink::env::test::set_caller::<ink::env::DefaultEnvironment>(accounts.bob);
+        assert_eq!(
+            contract.refund(vec![0]),
             Err(PSP34Error::Custom(String::from(
-                "DropspaceSale::buy: Wrong amount paid."
+                "DropspaceSale::refund: Refunds not available"
             )))
         );
     }
 
-
     #[ink::test]
-    fn buy2_works() {
+    fn rescue_psp34_guards_works() {
         let accounts = default_accounts();
 
         // Set owner
         ink::env::test::set_caller::<ink::env::DefaultEnvironment>(accounts.charlie);
 
         let params = ContractParam {
-            mint_fee: 0,
             withdraw_wallet: Some(accounts.django),
             dev_wallet: Some(accounts.eve),
             ..Default::default()
         };
         let mut contract = get_contract(&params);
+        let contract_id = ink::env::account_id::<ink::env::DefaultEnvironment>();
+        assert_eq!(contract.set_refund_window(1000, false), Ok(()));
+        ink::env::test::set_account_balance::<ink::env::DefaultEnvironment>(
+            accounts.bob,
+            100_000_000,
+        );
 
+        // A refundable token sent to the sale contract stays put until its window closes
         ink::env::test::set_caller::<ink::env::DefaultEnvironment>(accounts.bob);
+        assert_eq!(ink::env::pay_with_call!(contract.buy(1), 1010), Ok(()));
         assert_eq!(
-            ink::env::pay_with_call!(contract.buy(1), params.mint_price),
+            psp34::PSP34::transfer(&mut contract, contract_id, Id::U128(0), Vec::new()),
             Ok(())
         );
 
-        assert_eq!(psp34::PSP34::total_supply(&contract), 1);
+        ink::env::test::set_caller::<ink::env::DefaultEnvironment>(accounts.charlie);
+        assert_eq!(
+            contract.rescue_psp34(contract_id, Id::U128(0), accounts.frank),
+            Err(PSP34Error::Custom(String::from(
+                "DropspaceSale::rescue_psp34: Token still refundable"
+            )))
+        );
+
+        ink::env::test::set_block_timestamp::<ink::env::DefaultEnvironment>(1000);
+        assert_eq!(
+            contract.rescue_psp34(contract_id, Id::U128(0), accounts.frank),
+            Ok(())
+        );
+        assert_eq!(
+            psp34::PSP34::owner_of(&contract, Id::U128(0)),
+            Some(accounts.frank)
+        );
     }
 
     #[ink::test]
-    fn setters_work() {
+    fn refund_window_works() {
         let accounts = default_accounts();
 
         // Set owner
@@ -563,134 +5443,603 @@ mod tests {
 
         let params = ContractParam {
             withdraw_wallet: Some(accounts.django),
-            dev_wallet: Some(accounts.alice),
+            dev_wallet: Some(accounts.eve),
             ..Default::default()
         };
         let mut contract = get_contract(&params);
+        assert_eq!(contract.set_refund_window(1000, false), Ok(()));
+
+        let contract_id = ink::env::account_id::<ink::env::DefaultEnvironment>();
+        let minimum_balance = ink::env::minimum_balance::<ink::env::DefaultEnvironment>();
+        ink::env::test::set_account_balance::<ink::env::DefaultEnvironment>(
+            contract_id,
+            minimum_balance,
+        );
+        ink::env::test::set_account_balance::<ink::env::DefaultEnvironment>(accounts.django, 0);
+        ink::env::test::set_account_balance::<ink::env::DefaultEnvironment>(accounts.eve, 0);
+        ink::env::test::set_account_balance::<ink::env::DefaultEnvironment>(
+            accounts.bob,
+            100_000_000,
+        );
 
+        // The fee goes out right away, the price is held for refunds
+        ink::env::test::set_caller::<ink::env::DefaultEnvironment>(accounts.bob);
+        assert_eq!(ink::env::pay_with_call!(contract.buy(2), 2020), Ok(()));
         assert_eq!(
-            contract.set_base_uri("https://newuri.com/token/".to_string()),
-            Ok(())
+            ink::env::test::get_account_balance::<ink::env::DefaultEnvironment>(accounts.eve),
+            Ok(20)
         );
-        assert_eq!(contract.set_mint_per_tx(20), Ok(()));
-        assert_eq!(contract.set_mint_price(2000), Ok(()));
-        assert_eq!(contract.set_sale_time(87654321), Ok(()));
-        assert_eq!(contract.set_supply_limit(50000), Ok(()));
-        assert_eq!(contract.reserve(5), Ok(()));
+        assert_eq!(contract.refund_reserve(), (2000, 0));
+        assert_eq!(contract.refund_window_end(), 1000);
+        assert_eq!(contract.withdrawable(), 0);
+
+        ink::env::test::set_block_timestamp::<ink::env::DefaultEnvironment>(500);
+        assert_eq!(contract.refund(vec![0]), Ok(()));
         assert_eq!(
-            contract.set_supply_limit(1),
+            ink::env::test::get_account_balance::<ink::env::DefaultEnvironment>(accounts.bob),
+            Ok(100_000_000 - 2020 + 1000)
+        );
+        assert_eq!(contract.refund_reserve(), (1000, 0));
+        assert_eq!(contract.refunded(PaymentAsset::Native), 1000);
+
+        // A later purchase gets its own expiry
+        assert_eq!(ink::env::pay_with_call!(contract.buy(1), 1010), Ok(()));
+        assert_eq!(contract.refund_reserve(), (2000, 0));
+        assert_eq!(contract.refund_window_end(), 1500);
+
+        // Turning the window off doesn't clear what's still held for open refunds
+        ink::env::test::set_caller::<ink::env::DefaultEnvironment>(accounts.charlie);
+        assert_eq!(contract.set_refund_window(0, false), Ok(()));
+        assert_eq!(
+            contract.set_crowdfund(5000, 1, 2000),
             Err(PSP34Error::Custom(String::from(
-                "DropspaceSale::set_total_supply: Supply limit is lesser than current supply"
+                "DropspaceSale::set_crowdfund: Refund reserve still held"
+            )))
+        );
+        assert_eq!(
+            contract.release_refund_reserve(),
+            Err(PSP34Error::Custom(String::from(
+                "DropspaceSale::release_refund_reserve: Refund window still open"
+            )))
+        );
+
+        ink::env::test::set_block_timestamp::<ink::env::DefaultEnvironment>(1000);
+        ink::env::test::set_caller::<ink::env::DefaultEnvironment>(accounts.bob);
+        assert_eq!(
+            contract.refund(vec![1]),
+            Err(PSP34Error::Custom(String::from(
+                "DropspaceSale::refund: Refund window closed"
+            )))
+        );
+
+        // Only the matured part is released
+        ink::env::test::set_caller::<ink::env::DefaultEnvironment>(accounts.charlie);
+        assert_eq!(contract.release_refund_reserve(), Ok(()));
+        assert_eq!(
+            ink::env::test::get_account_balance::<ink::env::DefaultEnvironment>(accounts.django),
+            Ok(1000)
+        );
+        assert_eq!(contract.refund_reserve(), (1000, 0));
+        assert_eq!(contract.escrowed(), 1000);
+        assert_eq!(
+            contract.release_refund_reserve(),
+            Err(PSP34Error::Custom(String::from(
+                "DropspaceSale::release_refund_reserve: Refund window still open"
             )))
         );
+
+        ink::env::test::set_block_timestamp::<ink::env::DefaultEnvironment>(1500);
+        assert_eq!(contract.release_refund_reserve(), Ok(()));
+        assert_eq!(
+            ink::env::test::get_account_balance::<ink::env::DefaultEnvironment>(accounts.django),
+            Ok(2000)
+        );
+        assert_eq!(contract.refund_reserve(), (0, 0));
+        assert_eq!(contract.escrowed(), 0);
+        assert_eq!(contract.get_account_balance(), minimum_balance);
+        assert_eq!(contract.set_crowdfund(5000, 1, 2000), Ok(()));
     }
+}
 
-    #[ink::test]
-    fn sale_active_works() {
-        let accounts = default_accounts();
-        let params = ContractParam {
-            withdraw_wallet: Some(accounts.django),
-            dev_wallet: Some(accounts.alice),
-            ..Default::default()
-        };
-        let mut contract = get_contract(&params);
+#[cfg(all(test, feature = "e2e-tests"))]
+mod e2e_tests {
+    use super::dropspace_sale::{ContractRef, PaymentAsset};
+    use buy_proxy::buy_proxy::BuyProxyRef;
+    use ink_e2e::build_message;
+    use mock_psp22::mock_psp22::MockPsp22Ref;
+    use mock_psp34::mock_psp34::MockPsp34Ref;
+    use openbrush::contracts::psp22::psp22_external::PSP22;
+    use openbrush::contracts::psp34::psp34_external::PSP34;
+    use openbrush::contracts::psp34::{Id, PSP34Error};
+    use reentrant_psp22::reentrant_psp22::ReentrantPsp22Ref;
 
-        // Set the block timestamp to simulate sale time passing
-        ink::env::test::set_block_timestamp::<ink::env::DefaultEnvironment>(12345678);
-        assert_eq!(contract.sale_active(), true);
+    type E2EResult<T> = std::result::Result<T, Box<dyn std::error::Error>>;
 
-        let __ = contract.set_sale_time(12345679);
+    #[ink_e2e::test(additional_contracts = "mocks/psp22/Cargo.toml")]
+    async fn buy_with_psp22_works(mut client: ink_e2e::Client<C, E>) -> E2EResult<()> {
+        let bob = ink_e2e::account_id(ink_e2e::AccountKeyring::Bob);
+        let django = ink_e2e::account_id(ink_e2e::AccountKeyring::Dave);
+        let eve = ink_e2e::account_id(ink_e2e::AccountKeyring::Eve);
 
-        // After the sale time, sale should be active
-        assert_eq!(contract.sale_active(), false);
+        let token = client
+            .instantiate(
+                "mock_psp22",
+                &ink_e2e::bob(),
+                MockPsp22Ref::new(1_000_000),
+                0,
+                None,
+            )
+            .await
+            .expect("mock_psp22 instantiate failed")
+            .account_id;
 
-        let __ = contract.set_sale_time(0);
-        assert_eq!(contract.sale_active(), true);
+        let sale_constructor = ContractRef::new(
+            "Test".to_string(),
+            "TST".to_string(),
+            "https://example.com/token/".to_string(),
+            10,
+            1000,
+            10,
+            100000,
+            Some(django),
+            Some(eve),
+            0,
+        );
+        let sale = client
+            .instantiate(
+                "dropspace_sale",
+                &ink_e2e::alice(),
+                sale_constructor,
+                0,
+                None,
+            )
+            .await
+            .expect("dropspace_sale instantiate failed")
+            .account_id;
+
+        let set_payment = build_message::<ContractRef>(sale.clone())
+            .call(|contract| contract.add_payment_asset(PaymentAsset::Psp22(token), 500, 5));
+        client
+            .call(&ink_e2e::alice(), set_payment, 0, None)
+            .await
+            .expect("add_payment_asset failed");
+
+        let approve =
+            build_message::<MockPsp22Ref>(token.clone()).call(|psp22| psp22.approve(sale, 3 * 505));
+        client
+            .call(&ink_e2e::bob(), approve, 0, None)
+            .await
+            .expect("approve failed");
+
+        let buy = build_message::<ContractRef>(sale.clone())
+            .call(|contract| contract.buy_with_psp22(token, 3));
+        client
+            .call(&ink_e2e::bob(), buy, 0, None)
+            .await
+            .expect("buy_with_psp22 failed");
+
+        let balance_of =
+            build_message::<ContractRef>(sale.clone()).call(|contract| contract.balance_of(bob));
+        let minted = client
+            .call_dry_run(&ink_e2e::bob(), &balance_of, 0, None)
+            .await
+            .return_value();
+        assert_eq!(minted, 3);
+
+        for (wallet, expected) in [(django, 3 * 500), (eve, 3 * 5), (bob, 1_000_000 - 3 * 505)] {
+            let balance_of =
+                build_message::<MockPsp22Ref>(token.clone()).call(|psp22| psp22.balance_of(wallet));
+            let balance = client
+                .call_dry_run(&ink_e2e::bob(), &balance_of, 0, None)
+                .await
+                .return_value();
+            assert_eq!(balance, expected);
+        }
+
+        Ok(())
     }
 
-    #[ink::test]
-    fn toggle_sale_active_works() {
-        let accounts = default_accounts();
-        let params = ContractParam {
-            withdraw_wallet: Some(accounts.django),
-            dev_wallet: Some(accounts.alice),
-            ..Default::default()
-        };
+    #[ink_e2e::test(additional_contracts = "mocks/psp22/Cargo.toml")]
+    async fn rescue_psp22_works(mut client: ink_e2e::Client<C, E>) -> E2EResult<()> {
+        let bob = ink_e2e::account_id(ink_e2e::AccountKeyring::Bob);
 
-        // Set owner
-        ink::env::test::set_caller::<ink::env::DefaultEnvironment>(accounts.charlie);
+        let token = client
+            .instantiate(
+                "mock_psp22",
+                &ink_e2e::bob(),
+                MockPsp22Ref::new(1_000_000),
+                0,
+                None,
+            )
+            .await
+            .expect("mock_psp22 instantiate failed")
+            .account_id;
 
-        let mut contract = get_contract(&params);
+        let sale_constructor = ContractRef::new(
+            "Test".to_string(),
+            "TST".to_string(),
+            "https://example.com/token/".to_string(),
+            10,
+            1000,
+            10,
+            100000,
+            None,
+            None,
+            0,
+        );
+        let sale = client
+            .instantiate(
+                "dropspace_sale",
+                &ink_e2e::alice(),
+                sale_constructor,
+                0,
+                None,
+            )
+            .await
+            .expect("dropspace_sale instantiate failed")
+            .account_id;
 
-        // Ensure that only the owner can toggle sale active
-        ink::env::test::set_caller::<ink::env::DefaultEnvironment>(accounts.bob);
-        assert_eq!(
-            contract.toggle_sale_active(),
-            Err(PSP34Error::Custom(String::from("O::CallerIsNotOwner")))
+        // Bob sends tokens to the sale contract by mistake
+        let transfer = build_message::<MockPsp22Ref>(token.clone())
+            .call(|psp22| psp22.transfer(sale, 700, Vec::new()));
+        client
+            .call(&ink_e2e::bob(), transfer, 0, None)
+            .await
+            .expect("transfer failed");
+
+        let rescue = build_message::<ContractRef>(sale.clone())
+            .call(|contract| contract.rescue_psp22(token, bob, 700));
+        client
+            .call(&ink_e2e::alice(), rescue, 0, None)
+            .await
+            .expect("rescue_psp22 failed");
+
+        let balance_of =
+            build_message::<MockPsp22Ref>(token.clone()).call(|psp22| psp22.balance_of(bob));
+        let balance = client
+            .call_dry_run(&ink_e2e::bob(), &balance_of, 0, None)
+            .await
+            .return_value();
+        assert_eq!(balance, 1_000_000);
+
+        Ok(())
+    }
+
+    #[ink_e2e::test(additional_contracts = "mocks/reentrant_psp22/Cargo.toml")]
+    async fn buy_cannot_be_reentered(mut client: ink_e2e::Client<C, E>) -> E2EResult<()> {
+        let bob = ink_e2e::account_id(ink_e2e::AccountKeyring::Bob);
+        let django = ink_e2e::account_id(ink_e2e::AccountKeyring::Dave);
+        let eve = ink_e2e::account_id(ink_e2e::AccountKeyring::Eve);
+
+        let token = client
+            .instantiate(
+                "reentrant_psp22",
+                &ink_e2e::bob(),
+                ReentrantPsp22Ref::new(1_000_000),
+                0,
+                None,
+            )
+            .await
+            .expect("reentrant_psp22 instantiate failed")
+            .account_id;
+
+        let sale_constructor = ContractRef::new(
+            "Test".to_string(),
+            "TST".to_string(),
+            "https://example.com/token/".to_string(),
+            10,
+            1000,
+            10,
+            100000,
+            Some(django),
+            Some(eve),
+            0,
         );
+        let sale = client
+            .instantiate(
+                "dropspace_sale",
+                &ink_e2e::alice(),
+                sale_constructor,
+                0,
+                None,
+            )
+            .await
+            .expect("dropspace_sale instantiate failed")
+            .account_id;
 
-        assert_eq!(contract.sale_active(), true);
-        ink::env::test::set_caller::<ink::env::DefaultEnvironment>(accounts.charlie);
-        assert_eq!(contract.toggle_sale_active(), Ok(()));
-        assert_eq!(contract.sale_active(), false);
+        let set_payment = build_message::<ContractRef>(sale.clone())
+            .call(|contract| contract.add_payment_asset(PaymentAsset::Psp22(token), 500, 5));
+        client
+            .call(&ink_e2e::alice(), set_payment, 0, None)
+            .await
+            .expect("add_payment_asset failed");
 
-        // Simulate the owner calling the function
-        ink::env::test::set_caller::<ink::env::DefaultEnvironment>(accounts.charlie);
+        let set_target =
+            build_message::<ReentrantPsp22Ref>(token.clone()).call(|psp22| psp22.set_target(sale));
+        client
+            .call(&ink_e2e::bob(), set_target, 0, None)
+            .await
+            .expect("set_target failed");
 
-        // Toggle sale active, which should set the sale time to 0
-        assert_eq!(contract.toggle_sale_active(), Ok(()));
-        assert_eq!(contract.sale_time(), 0);
-        assert_eq!(contract.sale_active(), true);
+        let approve = build_message::<ReentrantPsp22Ref>(token.clone())
+            .call(|psp22| psp22.approve(sale, 505));
+        client
+            .call(&ink_e2e::bob(), approve, 0, None)
+            .await
+            .expect("approve failed");
 
-        // Toggle sale active again, which should set the sale time to u64::MAX
-        assert_eq!(contract.toggle_sale_active(), Ok(()));
-        assert_eq!(contract.sale_time(), u64::MAX);
-        assert_eq!(contract.sale_active(), false);
+        // `buy` itself can't be re-entered: it pays out with native `transfer`s, which run no
+        // callee code. A PSP22 payment token does run code mid-purchase, but the sale never
+        // allows reentry on its calls, so the runtime rejects the token's call back into `buy`
+        // and the whole purchase reverts before `non_reentrant` is even reached. The guard itself
+        // is covered by the `non_reentrant_works` unit test.
+        let buy = build_message::<ContractRef>(sale.clone())
+            .call(|contract| contract.buy_with_psp22(token, 1));
+        assert!(client.call(&ink_e2e::bob(), buy, 0, None).await.is_err());
+
+        let total_supply =
+            build_message::<ContractRef>(sale.clone()).call(|contract| contract.total_supply());
+        let minted = client
+            .call_dry_run(&ink_e2e::bob(), &total_supply, 0, None)
+            .await
+            .return_value();
+        assert_eq!(minted, 0);
+
+        let balance_of =
+            build_message::<ReentrantPsp22Ref>(token.clone()).call(|psp22| psp22.balance_of(bob));
+        let balance = client
+            .call_dry_run(&ink_e2e::bob(), &balance_of, 0, None)
+            .await
+            .return_value();
+        assert_eq!(balance, 1_000_000);
+
+        Ok(())
     }
 
-    #[ink::test]
-    fn withdraw_works() {
-        let accounts = default_accounts();
-        let mut contract = Contract::new(
+    #[ink_e2e::test(additional_contracts = "mocks/buy_proxy/Cargo.toml")]
+    async fn eoa_only_rejects_contract_callers(mut client: ink_e2e::Client<C, E>) -> E2EResult<()> {
+        let bob = ink_e2e::account_id(ink_e2e::AccountKeyring::Bob);
+        let django = ink_e2e::account_id(ink_e2e::AccountKeyring::Dave);
+        let eve = ink_e2e::account_id(ink_e2e::AccountKeyring::Eve);
+
+        let sale_constructor = ContractRef::new(
             "Test".to_string(),
             "TST".to_string(),
             "https://example.com/token/".to_string(),
-            10, // Assuming the price per token is 10
+            10,
             1000,
             10,
             100000,
-            Some(accounts.django),
-            Some(accounts.charlie),
-            0, // set sale time to 0 for testing
+            Some(django),
+            Some(eve),
+            0,
         );
+        let sale = client
+            .instantiate(
+                "dropspace_sale",
+                &ink_e2e::alice(),
+                sale_constructor,
+                0,
+                None,
+            )
+            .await
+            .expect("dropspace_sale instantiate failed")
+            .account_id;
 
-        // Simulate buying a token
-        ink::env::test::set_account_balance::<ink::env::DefaultEnvironment>(accounts.alice, 0);
-        ink::env::test::set_account_balance::<ink::env::DefaultEnvironment>(accounts.charlie, 0);
-        ink::env::test::set_account_balance::<ink::env::DefaultEnvironment>(
-            accounts.bob,
-            100_000_000,
+        let proxy = client
+            .instantiate("buy_proxy", &ink_e2e::bob(), BuyProxyRef::new(), 0, None)
+            .await
+            .expect("buy_proxy instantiate failed")
+            .account_id;
+
+        let set_eoa_only =
+            build_message::<ContractRef>(sale.clone()).call(|contract| contract.set_eoa_only(true));
+        client
+            .call(&ink_e2e::alice(), set_eoa_only, 0, None)
+            .await
+            .expect("set_eoa_only failed");
+
+        let proxy_buy =
+            build_message::<BuyProxyRef>(proxy.clone()).call(|proxy| proxy.buy(sale, 1));
+        let result = client
+            .call_dry_run(&ink_e2e::bob(), &proxy_buy, 1010, None)
+            .await
+            .return_value();
+        assert_eq!(
+            result,
+            Err(PSP34Error::Custom(String::from(
+                "DropspaceSale::buy: Contract callers not allowed"
+            )))
         );
 
-        ink::env::test::set_caller::<ink::env::DefaultEnvironment>(accounts.bob); // Setting the caller for the next contract call
-                                                                                  // Simulating value transferred for the next call - 10 tokens * 10 units each
-        assert_eq!(ink::env::pay_with_call!(contract.buy(10), 10_100), Ok(()));
-        assert_eq!(contract.get_account_balance(), 0);
+        let buy = build_message::<ContractRef>(sale.clone()).call(|contract| contract.buy(1));
+        client
+            .call(&ink_e2e::bob(), buy, 1010, None)
+            .await
+            .expect("buy failed");
 
-        // Check that owner's balance has increased by 10000 units
-        let dev_balance =
-            ink::env::test::get_account_balance::<ink::env::DefaultEnvironment>(accounts.charlie)
-                .unwrap_or_default();
-        assert_eq!(dev_balance, 100);
+        let balance_of =
+            build_message::<ContractRef>(sale.clone()).call(|contract| contract.balance_of(bob));
+        let owned = client
+            .call_dry_run(&ink_e2e::bob(), &balance_of, 0, None)
+            .await
+            .return_value();
+        assert_eq!(owned, 1);
 
-        // Simulate the owner calling the withdraw function
-        ink::env::test::set_caller::<ink::env::DefaultEnvironment>(accounts.alice);
-        assert_eq!(contract.withdraw(), Ok(()));
+        Ok(())
+    }
 
-        let owner_balance =
-            ink::env::test::get_account_balance::<ink::env::DefaultEnvironment>(accounts.alice)
-                .unwrap_or_default();
-        assert_eq!(owner_balance, 10000);
-        assert_eq!(contract.get_account_balance(), 0);
+    #[ink_e2e::test(additional_contracts = "mocks/psp34/Cargo.toml")]
+    async fn holder_claim_works(mut client: ink_e2e::Client<C, E>) -> E2EResult<()> {
+        let bob = ink_e2e::account_id(ink_e2e::AccountKeyring::Bob);
+        let charlie = ink_e2e::account_id(ink_e2e::AccountKeyring::Charlie);
+
+        let collection = client
+            .instantiate(
+                "mock_psp34",
+                &ink_e2e::alice(),
+                MockPsp34Ref::new(),
+                0,
+                None,
+            )
+            .await
+            .expect("mock_psp34 instantiate failed")
+            .account_id;
+        for (to, id) in [(bob, 1), (bob, 2), (charlie, 3)] {
+            let mint = build_message::<MockPsp34Ref>(collection.clone())
+                .call(|psp34| psp34.mint(to, Id::U128(id)));
+            client
+                .call(&ink_e2e::alice(), mint, 0, None)
+                .await
+                .expect("mint failed");
+        }
+
+        let sale_constructor = ContractRef::new(
+            "Test".to_string(),
+            "TST".to_string(),
+            "https://example.com/token/".to_string(),
+            10,
+            1000,
+            10,
+            100000,
+            None,
+            None,
+            0,
+        );
+        let sale = client
+            .instantiate(
+                "dropspace_sale",
+                &ink_e2e::alice(),
+                sale_constructor,
+                0,
+                None,
+            )
+            .await
+            .expect("dropspace_sale instantiate failed")
+            .account_id;
+
+        let set_holder_claim = build_message::<ContractRef>(sale.clone())
+            .call(|contract| contract.set_holder_claim(Some(collection), 0));
+        client
+            .call(&ink_e2e::alice(), set_holder_claim, 0, None)
+            .await
+            .expect("set_holder_claim failed");
+
+        let claim = build_message::<ContractRef>(sale.clone())
+            .call(|contract| contract.holder_claim(vec![Id::U128(1), Id::U128(2)]));
+        client
+            .call(&ink_e2e::bob(), claim, 0, None)
+            .await
+            .expect("holder_claim failed");
+
+        // Each held id can only be claimed once, and only by its holder
+        for ids in [vec![Id::U128(1)], vec![Id::U128(3)]] {
+            let claim = build_message::<ContractRef>(sale.clone())
+                .call(|contract| contract.holder_claim(ids.clone()));
+            assert!(client.call(&ink_e2e::bob(), claim, 0, None).await.is_err());
+        }
+
+        let balance_of =
+            build_message::<ContractRef>(sale.clone()).call(|contract| contract.balance_of(bob));
+        let owned = client
+            .call_dry_run(&ink_e2e::bob(), &balance_of, 0, None)
+            .await
+            .return_value();
+        assert_eq!(owned, 2);
+
+        Ok(())
+    }
+
+    #[ink_e2e::test(additional_contracts = "mocks/psp34/Cargo.toml")]
+    async fn redeem_works(mut client: ink_e2e::Client<C, E>) -> E2EResult<()> {
+        let bob = ink_e2e::account_id(ink_e2e::AccountKeyring::Bob);
+
+        let passes = client
+            .instantiate(
+                "mock_psp34",
+                &ink_e2e::alice(),
+                MockPsp34Ref::new(),
+                0,
+                None,
+            )
+            .await
+            .expect("mock_psp34 instantiate failed")
+            .account_id;
+        for id in [1, 2] {
+            let mint = build_message::<MockPsp34Ref>(passes.clone())
+                .call(|psp34| psp34.mint(bob, Id::U128(id)));
+            client
+                .call(&ink_e2e::alice(), mint, 0, None)
+                .await
+                .expect("mint failed");
+        }
+
+        let sale_constructor = ContractRef::new(
+            "Test".to_string(),
+            "TST".to_string(),
+            "https://example.com/token/".to_string(),
+            10,
+            1000,
+            10,
+            100000,
+            None,
+            None,
+            0,
+        );
+        let sale = client
+            .instantiate(
+                "dropspace_sale",
+                &ink_e2e::alice(),
+                sale_constructor,
+                0,
+                None,
+            )
+            .await
+            .expect("dropspace_sale instantiate failed")
+            .account_id;
+
+        let set_redemption = build_message::<ContractRef>(sale.clone())
+            .call(|contract| contract.set_redemption(Some(passes), 0, u64::MAX));
+        client
+            .call(&ink_e2e::alice(), set_redemption, 0, None)
+            .await
+            .expect("set_redemption failed");
+
+        let approve = build_message::<MockPsp34Ref>(passes.clone())
+            .call(|psp34| psp34.approve(sale, None, true));
+        client
+            .call(&ink_e2e::bob(), approve, 0, None)
+            .await
+            .expect("approve failed");
+
+        let redeem = build_message::<ContractRef>(sale.clone())
+            .call(|contract| contract.redeem(vec![Id::U128(1), Id::U128(2)]));
+        client
+            .call(&ink_e2e::bob(), redeem, 0, None)
+            .await
+            .expect("redeem failed");
+
+        // Passes are locked in the sale contract, new tokens went to the holder
+        let pass_owner =
+            build_message::<MockPsp34Ref>(passes.clone()).call(|psp34| psp34.owner_of(Id::U128(1)));
+        let owner = client
+            .call_dry_run(&ink_e2e::bob(), &pass_owner, 0, None)
+            .await
+            .return_value();
+        assert_eq!(owner, Some(sale));
+
+        let balance_of =
+            build_message::<ContractRef>(sale.clone()).call(|contract| contract.balance_of(bob));
+        let owned = client
+            .call_dry_run(&ink_e2e::bob(), &balance_of, 0, None)
+            .await
+            .return_value();
+        assert_eq!(owned, 2);
+
+        let redeem = build_message::<ContractRef>(sale.clone())
+            .call(|contract| contract.redeem(vec![Id::U128(1)]));
+        assert!(client.call(&ink_e2e::bob(), redeem, 0, None).await.is_err());
+
+        Ok(())
     }
 }
@@ -0,0 +1,37 @@
+#![cfg_attr(not(feature = "std"), no_std, no_main)]
+
+/// Contract that buys from the sale on behalf of its caller, used to check in e2e tests
+/// that `eoa_only` turns away contract callers.
+#[ink::contract]
+pub mod buy_proxy {
+    use ink::env::{
+        call::{build_call, ExecutionInput, Selector},
+        DefaultEnvironment,
+    };
+    use openbrush::contracts::psp34::PSP34Error;
+
+    #[ink(storage)]
+    #[derive(Default)]
+    pub struct BuyProxy {}
+
+    impl BuyProxy {
+        #[ink(constructor)]
+        pub fn new() -> Self {
+            Self::default()
+        }
+
+        /// Forwards the transferred value to the sale's `buy` and returns its result.
+        #[ink(message, payable)]
+        pub fn buy(&mut self, sale: AccountId, amount: u128) -> Result<(), PSP34Error> {
+            build_call::<DefaultEnvironment>()
+                .call(sale)
+                .transferred_value(self.env().transferred_value())
+                .exec_input(
+                    ExecutionInput::new(Selector::new(ink::selector_bytes!("buy")))
+                        .push_arg(amount),
+                )
+                .returns::<Result<(), PSP34Error>>()
+                .invoke()
+        }
+    }
+}
@@ -0,0 +1,25 @@
+#![cfg_attr(not(feature = "std"), no_std, no_main)]
+
+/// Plain PSP22 token used as a payment asset in the sale's e2e tests.
+#[openbrush::implementation(PSP22)]
+#[openbrush::contract]
+pub mod mock_psp22 {
+    use openbrush::traits::Storage;
+
+    #[ink(storage)]
+    #[derive(Default, Storage)]
+    pub struct MockPsp22 {
+        #[storage_field]
+        psp22: psp22::Data,
+    }
+
+    impl MockPsp22 {
+        #[ink(constructor)]
+        pub fn new(total_supply: Balance) -> Self {
+            let mut instance = Self::default();
+            psp22::Internal::_mint_to(&mut instance, Self::env().caller(), total_supply)
+                .expect("Should mint");
+            instance
+        }
+    }
+}
@@ -0,0 +1,28 @@
+#![cfg_attr(not(feature = "std"), no_std, no_main)]
+
+/// Plain PSP34 collection with open minting, used as a companion collection in the
+/// sale's e2e tests.
+#[openbrush::implementation(PSP34)]
+#[openbrush::contract]
+pub mod mock_psp34 {
+    use openbrush::traits::Storage;
+
+    #[ink(storage)]
+    #[derive(Default, Storage)]
+    pub struct MockPsp34 {
+        #[storage_field]
+        psp34: psp34::Data,
+    }
+
+    impl MockPsp34 {
+        #[ink(constructor)]
+        pub fn new() -> Self {
+            Self::default()
+        }
+
+        #[ink(message)]
+        pub fn mint(&mut self, to: AccountId, id: Id) -> Result<(), PSP34Error> {
+            psp34::Internal::_mint_to(self, to, id)
+        }
+    }
+}
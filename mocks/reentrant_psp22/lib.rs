@@ -0,0 +1,58 @@
+#![cfg_attr(not(feature = "std"), no_std, no_main)]
+
+/// PSP22 token that calls back into the sale's `buy` while a payment is being pulled,
+/// used to check in e2e tests that a payment token can't re-enter the sale.
+#[openbrush::implementation(PSP22)]
+#[openbrush::contract]
+pub mod reentrant_psp22 {
+    use ink::env::{
+        call::{build_call, ExecutionInput, Selector},
+        DefaultEnvironment,
+    };
+    use openbrush::{contracts::psp34::PSP34Error, traits::Storage};
+
+    #[ink(storage)]
+    #[derive(Default, Storage)]
+    pub struct ReentrantPsp22 {
+        #[storage_field]
+        psp22: psp22::Data,
+        target: Option<AccountId>,
+    }
+
+    #[overrider(psp22::Internal)]
+    fn _before_token_transfer(
+        &mut self,
+        from: Option<&AccountId>,
+        to: Option<&AccountId>,
+        _amount: &Balance,
+    ) -> Result<(), PSP22Error> {
+        if let (Some(_), Some(_), Some(target)) = (from, to, self.target) {
+            // The sale is still on the call stack, so this is only reached by `buy` if the
+            // sale allowed reentry on its call into this token.
+            let _ = build_call::<DefaultEnvironment>()
+                .call(target)
+                .exec_input(
+                    ExecutionInput::new(Selector::new(ink::selector_bytes!("buy"))).push_arg(1u128),
+                )
+                .returns::<Result<(), PSP34Error>>()
+                .try_invoke();
+        }
+        Ok(())
+    }
+
+    impl ReentrantPsp22 {
+        #[ink(constructor)]
+        pub fn new(total_supply: Balance) -> Self {
+            let mut instance = Self::default();
+            psp22::Internal::_mint_to(&mut instance, Self::env().caller(), total_supply)
+                .expect("Should mint");
+            instance
+        }
+
+        /// Makes every transfer try to re-enter `target`.
+        #[ink(message)]
+        pub fn set_target(&mut self, target: AccountId) {
+            self.target = Some(target);
+        }
+    }
+}